@@ -0,0 +1,280 @@
+//! Wrappers around [`ConfigRegionAccess`] implementations that add extra behaviour to configuration accesses.
+
+use crate::{debug_assert_dword_aligned, ConfigRegionAccess, PciAddress};
+use core::cell::RefCell;
+
+/// Errors that can be detected when reading configuration space through one of the wrappers in this module
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadError {
+    /// The read returned `0xffffffff`. This is what most platforms return for accesses that fail or target
+    /// functions that don't exist.
+    AllOnes,
+    /// No two consecutive reads returned the same value within the allowed number of retries. `last` is the value
+    /// of the last read.
+    Unstable { last: u32 },
+}
+
+/// Write `value` to the dword at `offset`, then read it back and check that the bits set in `mask` hold the value
+/// that was written. Returns `false` if they don't, which usually means some of the bits are read-only or
+/// reserved, or that the device dropped the write.
+///
+/// This costs an extra read, so is intended for bring-up and debugging rather than for every write. It isn't hidden
+/// behind `debug_assertions` or a feature: nothing pays for the read unless this is called, and gating it would
+/// make code that calls it fail to build in release mode.
+///
+/// # Safety
+/// See [`ConfigRegionAccess::write`].
+pub unsafe fn write_confirmed(
+    access: &impl ConfigRegionAccess,
+    address: PciAddress,
+    offset: u16,
+    value: u32,
+    mask: u32,
+) -> bool {
+    debug_assert_dword_aligned(offset);
+    access.write(address, offset, value);
+    access.read(address, offset) & mask == value & mask
+}
+
+/// Wraps an access and offers [`CheckedAccess::try_read`], which treats reads returning all ones as errors.
+///
+/// This is a heuristic: some registers can legitimately read as `0xffffffff` (e.g. a BAR being sized), so it is only
+/// applied by `try_read`. Used as a plain [`ConfigRegionAccess`], this passes every access through unchanged.
+pub struct CheckedAccess<A: ConfigRegionAccess> {
+    inner: A,
+}
+
+impl<A: ConfigRegionAccess> CheckedAccess<A> {
+    pub fn new(inner: A) -> CheckedAccess<A> {
+        CheckedAccess { inner }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    /// Read the dword at `offset`, returning [`ReadError::AllOnes`] if it reads as `0xffffffff`.
+    ///
+    /// # Safety
+    /// See [`ConfigRegionAccess::read`].
+    pub unsafe fn try_read(&self, address: PciAddress, offset: u16) -> Result<u32, ReadError> {
+        debug_assert_dword_aligned(offset);
+        match self.inner.read(address, offset) {
+            0xffffffff => Err(ReadError::AllOnes),
+            value => Ok(value),
+        }
+    }
+}
+
+impl<A: ConfigRegionAccess> ConfigRegionAccess for CheckedAccess<A> {
+    fn function_exists(&self, address: PciAddress) -> bool {
+        self.inner.function_exists(address)
+    }
+
+    fn supports_extended(&self) -> bool {
+        self.inner.supports_extended()
+    }
+
+    unsafe fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        self.inner.read(address, offset)
+    }
+
+    unsafe fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        debug_assert_dword_aligned(offset);
+        self.inner.write(address, offset, value)
+    }
+
+    unsafe fn read_block(&self, address: PciAddress, offset: u16, buffer: &mut [u32]) {
+        debug_assert_dword_aligned(offset);
+        self.inner.read_block(address, offset, buffer)
+    }
+
+    unsafe fn read_u8(&self, address: PciAddress, offset: u16) -> u8 {
+        self.inner.read_u8(address, offset)
+    }
+
+    unsafe fn read_u16(&self, address: PciAddress, offset: u16) -> u16 {
+        self.inner.read_u16(address, offset)
+    }
+
+    unsafe fn write_u8(&self, address: PciAddress, offset: u16, value: u8) {
+        self.inner.write_u8(address, offset, value)
+    }
+
+    unsafe fn write_u16(&self, address: PciAddress, offset: u16, value: u16) {
+        self.inner.write_u16(address, offset, value)
+    }
+}
+
+/// Wraps an access whose reads occasionally return transient garbage, such as some unreliable MMCONFIG
+/// implementations. Every read is repeated until two consecutive reads agree, up to the number of retries given
+/// at construction. Writes are passed through unchanged.
+///
+/// As every read is done at least twice, this should not be used with registers that have side effects on read.
+pub struct RetryingAccess<A: ConfigRegionAccess> {
+    inner: A,
+    max_retries: usize,
+}
+
+impl<A: ConfigRegionAccess> RetryingAccess<A> {
+    /// Wrap `inner`, re-reading at most `max_retries` times after the first two reads disagree
+    pub fn new(inner: A, max_retries: usize) -> RetryingAccess<A> {
+        RetryingAccess { inner, max_retries }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    /// Read the dword at `offset` until two consecutive reads agree, returning [`ReadError::Unstable`] with the
+    /// value of the last read if they never do.
+    ///
+    /// # Safety
+    /// See [`ConfigRegionAccess::read`].
+    pub unsafe fn try_read(&self, address: PciAddress, offset: u16) -> Result<u32, ReadError> {
+        debug_assert_dword_aligned(offset);
+        let mut previous = self.inner.read(address, offset);
+        for _ in 0..=self.max_retries {
+            let current = self.inner.read(address, offset);
+            if current == previous {
+                return Ok(current);
+            }
+            previous = current;
+        }
+        Err(ReadError::Unstable { last: previous })
+    }
+}
+
+impl<A: ConfigRegionAccess> ConfigRegionAccess for RetryingAccess<A> {
+    fn function_exists(&self, address: PciAddress) -> bool {
+        self.inner.function_exists(address)
+    }
+
+    fn supports_extended(&self) -> bool {
+        self.inner.supports_extended()
+    }
+
+    /// Read the dword at `offset` until two consecutive reads agree. If they never do, the value of the last
+    /// read is returned.
+    unsafe fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        match self.try_read(address, offset) {
+            Ok(value) | Err(ReadError::Unstable { last: value }) => value,
+            Err(ReadError::AllOnes) => unreachable!("try_read doesn't check for all ones"),
+        }
+    }
+
+    unsafe fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        debug_assert_dword_aligned(offset);
+        self.inner.write(address, offset, value)
+    }
+}
+
+/// Whether an access was a read or a write
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A single access made through a [`LoggingAccess`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessEvent {
+    pub address: PciAddress,
+    pub offset: u16,
+    pub kind: AccessKind,
+    /// The size of the access, in bytes (`1`, `2` or `4`)
+    pub width: u8,
+    /// For reads, the value that was read. For writes, the value that was written.
+    pub value: u32,
+}
+
+/// Wraps an access and calls a closure with every access made through it, as it happens. Unlike
+/// `testing::TracingAccess`, nothing is kept, so this doesn't need `alloc` and can stream accesses to a
+/// logger while debugging.
+///
+/// Reads are reported once they complete, and writes just before they are made. Byte and word accesses are reported
+/// with their own width, even if the wrapped access implements them on top of dword accesses.
+pub struct LoggingAccess<A: ConfigRegionAccess, F: FnMut(AccessEvent) + Send> {
+    inner: A,
+    log: RefCell<F>,
+}
+
+impl<A: ConfigRegionAccess, F: FnMut(AccessEvent) + Send> LoggingAccess<A, F> {
+    pub fn new(inner: A, log: F) -> LoggingAccess<A, F> {
+        LoggingAccess {
+            inner,
+            log: RefCell::new(log),
+        }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    fn emit(&self, address: PciAddress, offset: u16, kind: AccessKind, width: u8, value: u32) {
+        (self.log.borrow_mut())(AccessEvent {
+            address,
+            offset,
+            kind,
+            width,
+            value,
+        });
+    }
+}
+
+impl<A: ConfigRegionAccess, F: FnMut(AccessEvent) + Send> ConfigRegionAccess
+    for LoggingAccess<A, F>
+{
+    fn function_exists(&self, address: PciAddress) -> bool {
+        self.inner.function_exists(address)
+    }
+
+    fn supports_extended(&self) -> bool {
+        self.inner.supports_extended()
+    }
+
+    unsafe fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        let value = self.inner.read(address, offset);
+        self.emit(address, offset, AccessKind::Read, 4, value);
+        value
+    }
+
+    unsafe fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        debug_assert_dword_aligned(offset);
+        self.emit(address, offset, AccessKind::Write, 4, value);
+        self.inner.write(address, offset, value)
+    }
+
+    unsafe fn read_block(&self, address: PciAddress, offset: u16, buffer: &mut [u32]) {
+        debug_assert_dword_aligned(offset);
+        self.inner.read_block(address, offset, buffer);
+        for (i, value) in buffer.iter().enumerate() {
+            self.emit(address, offset + i as u16 * 4, AccessKind::Read, 4, *value);
+        }
+    }
+
+    unsafe fn read_u8(&self, address: PciAddress, offset: u16) -> u8 {
+        let value = self.inner.read_u8(address, offset);
+        self.emit(address, offset, AccessKind::Read, 1, value as u32);
+        value
+    }
+
+    unsafe fn read_u16(&self, address: PciAddress, offset: u16) -> u16 {
+        let value = self.inner.read_u16(address, offset);
+        self.emit(address, offset, AccessKind::Read, 2, value as u32);
+        value
+    }
+
+    unsafe fn write_u8(&self, address: PciAddress, offset: u16, value: u8) {
+        self.emit(address, offset, AccessKind::Write, 1, value as u32);
+        self.inner.write_u8(address, offset, value)
+    }
+
+    unsafe fn write_u16(&self, address: PciAddress, offset: u16, value: u16) {
+        self.emit(address, offset, AccessKind::Write, 2, value as u32);
+        self.inner.write_u16(address, offset, value)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{debug_assert_dword_aligned, ConfigRegionAccess, PciAddress};
+use core::ops::Index;
+
+/// A copy of a function's configuration space held in a fixed-size buffer, e.g. for parsing a dump of it without
+/// any heap allocation. `N` is usually `256` for a PCI configuration space or `4096` for a PCI Express one.
+///
+/// As a [`ConfigRegionAccess`], it responds to every address with the same configuration space. It is read-only:
+/// writes are discarded, so operations that rely on them (such as probing the size of a BAR) won't give
+/// meaningful results. Reads that extend past the end of the buffer return `0xffffffff`, like reads of a
+/// non-existent register on real hardware.
+#[derive(Clone)]
+pub struct ConfigBuffer<const N: usize> {
+    bytes: [u8; N],
+}
+
+impl<const N: usize> ConfigBuffer<N> {
+    pub fn new(bytes: [u8; N]) -> ConfigBuffer<N> {
+        ConfigBuffer { bytes }
+    }
+
+    /// Copy a configuration space out of `bytes`. If `bytes` is shorter than `N`, the rest of the buffer is
+    /// zeroed, and if it is longer, the extra bytes are ignored.
+    pub fn from_slice(bytes: &[u8]) -> ConfigBuffer<N> {
+        let mut buffer = [0; N];
+        let len = bytes.len().min(N);
+        buffer[..len].copy_from_slice(&bytes[..len]);
+        ConfigBuffer { bytes: buffer }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.bytes
+    }
+
+    /// The byte at `offset`, or `None` if it is past the end of the buffer
+    pub fn get(&self, offset: u16) -> Option<u8> {
+        self.bytes.get(offset as usize).copied()
+    }
+}
+
+/// Index the buffer by byte offset. Panics if `offset` is past the end of the buffer; use [`ConfigBuffer::get`]
+/// to probe offsets that might be.
+impl<const N: usize> Index<u16> for ConfigBuffer<N> {
+    type Output = u8;
+
+    fn index(&self, offset: u16) -> &u8 {
+        &self.bytes[offset as usize]
+    }
+}
+
+impl<const N: usize> ConfigRegionAccess for ConfigBuffer<N> {
+    fn function_exists(&self, _address: PciAddress) -> bool {
+        N >= 2 && u16::from_le_bytes([self.bytes[0], self.bytes[1]]) != 0xffff
+    }
+
+    /// Buffers longer than 256 bytes hold (part of) the extended configuration space
+    fn supports_extended(&self) -> bool {
+        N > 0x100
+    }
+
+    unsafe fn read(&self, _address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        let offset = offset as usize;
+        match self.bytes.get(offset..offset + 4) {
+            Some(dword) => u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]]),
+            None => 0xffffffff,
+        }
+    }
+
+    unsafe fn write(&self, _address: PciAddress, offset: u16, _value: u32) {
+        debug_assert_dword_aligned(offset);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use bit_field::BitField;
+use core::{
+    fmt::{Debug, Formatter},
+    ops::{BitAnd, BitOr, Not},
+};
+
+/// A set of Access Control Services features. The ACS Capability register uses this to report which features a
+/// function implements, and the ACS Control register uses the same bits to enable them.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct AcsFeatures(u16);
+
+impl AcsFeatures {
+    /// Check that the Requester ID of upstream requests belongs to the bus range behind the port
+    pub const SOURCE_VALIDATION: Self = AcsFeatures(1 << 0);
+    /// Block upstream requests with the Address Translation field set to anything but untranslated
+    pub const TRANSLATION_BLOCKING: Self = AcsFeatures(1 << 1);
+    /// Send peer-to-peer requests upstream instead of routing them directly to the peer
+    pub const P2P_REQUEST_REDIRECT: Self = AcsFeatures(1 << 2);
+    /// Send peer-to-peer completions upstream instead of routing them directly to the peer
+    pub const P2P_COMPLETION_REDIRECT: Self = AcsFeatures(1 << 3);
+    /// Stop requests and completions that came from upstream from being forwarded back upstream
+    pub const UPSTREAM_FORWARDING: Self = AcsFeatures(1 << 4);
+    /// Block or redirect peer-to-peer requests according to the Egress Control Vector
+    pub const P2P_EGRESS_CONTROL: Self = AcsFeatures(1 << 5);
+    /// Route peer-to-peer requests with translated addresses directly, even if they would otherwise be redirected
+    pub const DIRECT_TRANSLATED_P2P: Self = AcsFeatures(1 << 6);
+
+    /// Bits of the registers that correspond to a feature. Control bits above these belong to the enhanced
+    /// capability and are not modeled.
+    const ALL_BITS: u16 = 0x007f;
+
+    /// The empty set
+    pub const fn empty() -> Self {
+        AcsFeatures(0)
+    }
+
+    /// The set of every feature defined by the specification
+    pub const fn all() -> Self {
+        AcsFeatures(Self::ALL_BITS)
+    }
+
+    /// Create a set from the raw value of a register. Bits that don't correspond to a feature are discarded.
+    pub const fn from_bits(bits: u16) -> Self {
+        AcsFeatures(bits & Self::ALL_BITS)
+    }
+
+    /// Raw value of the set, as written to the registers
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Will be `true` if every feature in `other` is also in this set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for AcsFeatures {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        AcsFeatures(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for AcsFeatures {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        AcsFeatures(self.0 & rhs.0)
+    }
+}
+
+impl Not for AcsFeatures {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        AcsFeatures(!self.0 & Self::ALL_BITS)
+    }
+}
+
+impl Debug for AcsFeatures {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AcsFeatures({:#06x})", self.0)
+    }
+}
+
+/// Errors that can occur when configuring Access Control Services
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcsError {
+    /// Some of the requested features are not implemented by the function. Carries the features that are
+    /// missing.
+    NotSupported(AcsFeatures),
+}
+
+/// Access Control Services extended capability, found on downstream ports and multi-function devices. ACS
+/// controls whether peer-to-peer traffic may bypass the root complex, which is what an IOMMU relies on to isolate
+/// functions from each other.
+#[derive(Debug, Clone)]
+pub struct AcsCapability {
+    address: PciCapabilityAddress,
+}
+
+impl AcsCapability {
+    /// Interpret an extended capability as an ACS capability. Returns `None` if it is a different capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<AcsCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::AccessControlServices => Some(AcsCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The features the function implements
+    pub fn supported(&self, access: &impl ConfigRegionAccess) -> AcsFeatures {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        AcsFeatures::from_bits(reg.get_bits(0..16) as u16)
+    }
+
+    /// The number of bits in the Egress Control Vector, if P2P Egress Control is supported. An encoding of `0`
+    /// means 256 bits.
+    pub fn egress_control_vector_size(&self, access: &impl ConfigRegionAccess) -> u16 {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        match reg.get_bits(8..16) {
+            0 => 256,
+            size => size as u16,
+        }
+    }
+
+    /// The features that are currently enabled
+    pub fn control(&self, access: &impl ConfigRegionAccess) -> AcsFeatures {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        AcsFeatures::from_bits(reg.get_bits(16..32) as u16)
+    }
+
+    /// Enable exactly the features in `features`, and disable every other one. Fails without changing anything
+    /// if a feature is not supported by the function, as its control bit is hardwired to `0` and enabling it
+    /// would silently do nothing.
+    pub fn set_control(
+        &self,
+        access: &impl ConfigRegionAccess,
+        features: AcsFeatures,
+    ) -> Result<(), AcsError> {
+        let unsupported = features & !self.supported(access);
+        if !unsupported.is_empty() {
+            return Err(AcsError::NotSupported(unsupported));
+        }
+
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        /*
+         * Only the bits modeled by `AcsFeatures` are replaced, so the enhanced control fields keep their values.
+         */
+        let mut control = reg.get_bits(16..32) as u16 & !AcsFeatures::ALL_BITS;
+        control |= features.bits();
+        reg.set_bits(16..32, control as u32);
+        unsafe { access.write(self.address.address, self.address.offset + 0x4, reg) };
+        Ok(())
+    }
+
+    /// Enable the features in `features`, leaving the ones already enabled as they are. See
+    /// [`AcsCapability::set_control`].
+    pub fn enable(
+        &self,
+        access: &impl ConfigRegionAccess,
+        features: AcsFeatures,
+    ) -> Result<(), AcsError> {
+        self.set_control(access, self.control(access) | features)
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use core::{
+    fmt::{Debug, Formatter},
+    ops::{BitAnd, BitOr, Not},
+};
+
+/// A set of uncorrectable errors, as used by the Uncorrectable Error Status, Mask and Severity registers of the
+/// Advanced Error Reporting capability. Sets can be combined with `|`, e.g. to mask ECRC errors and Unsupported
+/// Requests:
+/// ```ignore
+/// aer.set_uncorrectable_mask(access, UncorrectableErrors::ECRC_ERROR | UncorrectableErrors::UNSUPPORTED_REQUEST);
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct UncorrectableErrors(u32);
+
+impl UncorrectableErrors {
+    pub const DATA_LINK_PROTOCOL_ERROR: Self = UncorrectableErrors(1 << 4);
+    pub const SURPRISE_DOWN: Self = UncorrectableErrors(1 << 5);
+    pub const POISONED_TLP: Self = UncorrectableErrors(1 << 12);
+    pub const FLOW_CONTROL_PROTOCOL_ERROR: Self = UncorrectableErrors(1 << 13);
+    pub const COMPLETION_TIMEOUT: Self = UncorrectableErrors(1 << 14);
+    pub const COMPLETER_ABORT: Self = UncorrectableErrors(1 << 15);
+    pub const UNEXPECTED_COMPLETION: Self = UncorrectableErrors(1 << 16);
+    pub const RECEIVER_OVERFLOW: Self = UncorrectableErrors(1 << 17);
+    pub const MALFORMED_TLP: Self = UncorrectableErrors(1 << 18);
+    pub const ECRC_ERROR: Self = UncorrectableErrors(1 << 19);
+    pub const UNSUPPORTED_REQUEST: Self = UncorrectableErrors(1 << 20);
+    pub const ACS_VIOLATION: Self = UncorrectableErrors(1 << 21);
+    pub const INTERNAL_ERROR: Self = UncorrectableErrors(1 << 22);
+    pub const MC_BLOCKED_TLP: Self = UncorrectableErrors(1 << 23);
+    pub const ATOMIC_OP_EGRESS_BLOCKED: Self = UncorrectableErrors(1 << 24);
+    pub const TLP_PREFIX_BLOCKED: Self = UncorrectableErrors(1 << 25);
+    pub const POISONED_TLP_EGRESS_BLOCKED: Self = UncorrectableErrors(1 << 26);
+
+    /// Bits of the registers that correspond to an error. The remaining bits are reserved.
+    const ALL_BITS: u32 = 0x07ff_f030;
+
+    /// The empty set
+    pub const fn empty() -> Self {
+        UncorrectableErrors(0)
+    }
+
+    /// The set of every error defined by the specification
+    pub const fn all() -> Self {
+        UncorrectableErrors(Self::ALL_BITS)
+    }
+
+    /// Create a set from the raw value of a register. Reserved bits are discarded.
+    pub const fn from_bits(bits: u32) -> Self {
+        UncorrectableErrors(bits & Self::ALL_BITS)
+    }
+
+    /// Raw value of the set, as written to the registers
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Will be `true` if every error in `other` is also in this set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for UncorrectableErrors {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        UncorrectableErrors(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for UncorrectableErrors {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        UncorrectableErrors(self.0 & rhs.0)
+    }
+}
+
+impl Not for UncorrectableErrors {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        UncorrectableErrors(!self.0 & Self::ALL_BITS)
+    }
+}
+
+impl Debug for UncorrectableErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "UncorrectableErrors({:#010x})", self.0)
+    }
+}
+
+/// A set of correctable errors, as used by the Correctable Error Status and Mask registers of the Advanced Error
+/// Reporting capability
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct CorrectableErrors(u32);
+
+impl CorrectableErrors {
+    pub const RECEIVER_ERROR: Self = CorrectableErrors(1 << 0);
+    pub const BAD_TLP: Self = CorrectableErrors(1 << 6);
+    pub const BAD_DLLP: Self = CorrectableErrors(1 << 7);
+    pub const REPLAY_NUM_ROLLOVER: Self = CorrectableErrors(1 << 8);
+    pub const REPLAY_TIMER_TIMEOUT: Self = CorrectableErrors(1 << 12);
+    pub const ADVISORY_NON_FATAL: Self = CorrectableErrors(1 << 13);
+    pub const INTERNAL_ERROR: Self = CorrectableErrors(1 << 14);
+    pub const HEADER_LOG_OVERFLOW: Self = CorrectableErrors(1 << 15);
+
+    /// Bits of the registers that correspond to an error. The remaining bits are reserved.
+    const ALL_BITS: u32 = 0x0000_f1c1;
+
+    /// The empty set
+    pub const fn empty() -> Self {
+        CorrectableErrors(0)
+    }
+
+    /// The set of every error defined by the specification
+    pub const fn all() -> Self {
+        CorrectableErrors(Self::ALL_BITS)
+    }
+
+    /// Create a set from the raw value of a register. Reserved bits are discarded.
+    pub const fn from_bits(bits: u32) -> Self {
+        CorrectableErrors(bits & Self::ALL_BITS)
+    }
+
+    /// Raw value of the set, as written to the registers
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Will be `true` if every error in `other` is also in this set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for CorrectableErrors {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        CorrectableErrors(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for CorrectableErrors {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        CorrectableErrors(self.0 & rhs.0)
+    }
+}
+
+impl Not for CorrectableErrors {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        CorrectableErrors(!self.0 & Self::ALL_BITS)
+    }
+}
+
+impl Debug for CorrectableErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "CorrectableErrors({:#010x})", self.0)
+    }
+}
+
+/// Advanced Error Reporting extended capability, which gives finer-grained control over error reporting than the
+/// PCI Express capability, and logs the errors that occurred.
+#[derive(Debug, Clone)]
+pub struct AdvancedErrorReportingCapability {
+    address: PciCapabilityAddress,
+}
+
+impl AdvancedErrorReportingCapability {
+    /// Interpret an extended capability as an Advanced Error Reporting capability. Returns `None` if it is a
+    /// different capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<AdvancedErrorReportingCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::AdvancedErrorReporting => {
+                Some(AdvancedErrorReportingCapability {
+                    address: capability.address().clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Read the Uncorrectable Error Status register, holding the uncorrectable errors that have been detected
+    pub fn uncorrectable_status(&self, access: &impl ConfigRegionAccess) -> UncorrectableErrors {
+        UncorrectableErrors::from_bits(self.read(access, 0x4))
+    }
+
+    /// Clear the given errors in the Uncorrectable Error Status register
+    pub fn clear_uncorrectable_status(
+        &self,
+        access: &impl ConfigRegionAccess,
+        errors: UncorrectableErrors,
+    ) {
+        /*
+         * The register is RW1C, so writing back only the given bits clears them and leaves the others alone.
+         */
+        self.write(access, 0x4, errors.bits());
+    }
+
+    /// Read the Uncorrectable Error Mask register. Masked errors are not logged or reported.
+    pub fn uncorrectable_mask(&self, access: &impl ConfigRegionAccess) -> UncorrectableErrors {
+        UncorrectableErrors::from_bits(self.read(access, 0x8))
+    }
+
+    /// Set the Uncorrectable Error Mask register, so that exactly the given errors are masked
+    pub fn set_uncorrectable_mask(
+        &self,
+        access: &impl ConfigRegionAccess,
+        errors: UncorrectableErrors,
+    ) {
+        self.write(access, 0x8, errors.bits());
+    }
+
+    /// Read the Uncorrectable Error Severity register. Errors in the set are reported as fatal; the others are
+    /// reported as non-fatal.
+    pub fn uncorrectable_severity(&self, access: &impl ConfigRegionAccess) -> UncorrectableErrors {
+        UncorrectableErrors::from_bits(self.read(access, 0xc))
+    }
+
+    /// Set the Uncorrectable Error Severity register, so that exactly the given errors are reported as fatal
+    pub fn set_uncorrectable_severity(
+        &self,
+        access: &impl ConfigRegionAccess,
+        fatal: UncorrectableErrors,
+    ) {
+        self.write(access, 0xc, fatal.bits());
+    }
+
+    /// Read the Correctable Error Status register, holding the correctable errors that have been detected
+    pub fn correctable_status(&self, access: &impl ConfigRegionAccess) -> CorrectableErrors {
+        CorrectableErrors::from_bits(self.read(access, 0x10))
+    }
+
+    /// Clear the given errors in the Correctable Error Status register
+    pub fn clear_correctable_status(
+        &self,
+        access: &impl ConfigRegionAccess,
+        errors: CorrectableErrors,
+    ) {
+        self.write(access, 0x10, errors.bits());
+    }
+
+    /// Read the Correctable Error Mask register. Masked errors are not reported.
+    pub fn correctable_mask(&self, access: &impl ConfigRegionAccess) -> CorrectableErrors {
+        CorrectableErrors::from_bits(self.read(access, 0x14))
+    }
+
+    /// Set the Correctable Error Mask register, so that exactly the given errors are masked
+    pub fn set_correctable_mask(
+        &self,
+        access: &impl ConfigRegionAccess,
+        errors: CorrectableErrors,
+    ) {
+        self.write(access, 0x14, errors.bits());
+    }
+
+    fn read(&self, access: &impl ConfigRegionAccess, offset: u16) -> u32 {
+        unsafe { access.read(self.address.address, self.address.offset + offset) }
+    }
+
+    fn write(&self, access: &impl ConfigRegionAccess, offset: u16, value: u32) {
+        unsafe { access.write(self.address.address, self.address.offset + offset, value) }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess};
+use bit_field::BitField;
+
+/// Errors that can occur when operating on the Advanced Features capability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdvancedFeaturesError {
+    /// The function doesn't advertise Function Level Reset support in its AF Capabilities register
+    FlrNotSupported,
+    /// The function still had transactions pending after polling for the given number of iterations
+    TransactionsPending,
+}
+
+/// PCI Advanced Features capability, which gives conventional PCI functions a way of supporting Function Level
+/// Reset without a PCI Express capability
+#[derive(Debug, Clone)]
+pub struct AdvancedFeaturesCapability {
+    address: PciCapabilityAddress,
+    capabilities: u8,
+}
+
+impl AdvancedFeaturesCapability {
+    pub(crate) fn new(address: PciCapabilityAddress, extension: u16) -> AdvancedFeaturesCapability {
+        AdvancedFeaturesCapability {
+            address,
+            capabilities: extension.get_bits(8..16) as u8,
+        }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// Will be `true` if the function supports the Transactions Pending bit of the AF Status register
+    pub fn transactions_pending_capable(&self) -> bool {
+        self.capabilities.get_bit(0)
+    }
+
+    /// Will be `true` if the function supports Function Level Reset through this capability
+    pub fn flr_capable(&self) -> bool {
+        self.capabilities.get_bit(1)
+    }
+
+    /// Will be `true` if the function has issued requests that haven't completed yet. Always `false` if the
+    /// function doesn't support this bit.
+    pub fn transactions_pending(&self, access: &impl ConfigRegionAccess) -> bool {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        reg.get_bit(8)
+    }
+
+    /// Initiate a Function Level Reset of this function. If the function supports it, the Transactions Pending bit
+    /// is first polled for up to `max_iterations` reads until it clears, as resetting the function while requests
+    /// are outstanding can leave their completions with nowhere to go.
+    ///
+    /// # Note
+    /// As with the PCI Express mechanism, the function must be given 100ms to come out of the reset before it is
+    /// accessed again, and has to be reconfigured afterwards.
+    pub fn initiate_flr(
+        &self,
+        access: &impl ConfigRegionAccess,
+        max_iterations: usize,
+    ) -> Result<(), AdvancedFeaturesError> {
+        if !self.flr_capable() {
+            return Err(AdvancedFeaturesError::FlrNotSupported);
+        }
+        if self.transactions_pending_capable()
+            && !(0..max_iterations).any(|_| !self.transactions_pending(access))
+        {
+            return Err(AdvancedFeaturesError::TransactionsPending);
+        }
+
+        /*
+         * The AF Status register in the byte above the AF Control register is RW1C, so only the Initiate FLR bit is
+         * written.
+         */
+        unsafe { access.write(self.address.address, self.address.offset + 0x4, 1) };
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use bit_field::BitField;
+
+/// Alternative Routing-ID Interpretation (ARI) extended capability. Under ARI, the Device Number field of a
+/// routing ID is reinterpreted as part of the Function Number, so a device can have up to 256 functions. See
+/// [`crate::PciAddress::new_ari`] for addressing such functions.
+#[derive(Debug, Clone)]
+pub struct AriCapability {
+    address: PciCapabilityAddress,
+}
+
+impl AriCapability {
+    /// Interpret an extended capability as an ARI capability. Returns `None` if it is a different capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<AriCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::AlternativeRoutingId => Some(AriCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The function number of the next function of the device, or `0` if this is the last one. Following this
+    /// from function 0 visits every function of an ARI device, which may have numbers above 7.
+    pub fn next_function_number(&self, access: &impl ConfigRegionAccess) -> u8 {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        reg.get_bits(8..16) as u8
+    }
+
+    /// Does the function support assigning functions to Multi-Function Virtual Channel function groups?
+    pub fn mfvc_function_groups_capable(&self, access: &impl ConfigRegionAccess) -> bool {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        reg.get_bit(0)
+    }
+
+    /// Does the function support assigning functions to Access Control Services function groups?
+    pub fn acs_function_groups_capable(&self, access: &impl ConfigRegionAccess) -> bool {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        reg.get_bit(1)
+    }
+
+    /// The function group this function is assigned to
+    pub fn function_group(&self, access: &impl ConfigRegionAccess) -> u8 {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        reg.get_bits(20..23) as u8
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use bit_field::BitField;
+
+/// Device Serial Number extended capability, holding a 64-bit serial number that is unique to the device (an
+/// EUI-64)
+#[derive(Debug, Clone)]
+pub struct DeviceSerialNumberCapability {
+    address: PciCapabilityAddress,
+}
+
+impl DeviceSerialNumberCapability {
+    /// Interpret an extended capability as a Device Serial Number capability. Returns `None` if it is a different
+    /// capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<DeviceSerialNumberCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::DeviceSerialNumber => Some(DeviceSerialNumberCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Read the serial number of the device
+    pub fn serial_number(&self, access: &impl ConfigRegionAccess) -> u64 {
+        let low = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        let high = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        let mut serial = low as u64;
+        serial.set_bits(32..64, high as u64);
+        serial
+    }
+}
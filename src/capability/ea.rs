@@ -0,0 +1,214 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess, HeaderType, PciHeader};
+use bit_field::BitField;
+
+/// Which BAR (or other resource) an Enhanced Allocation entry stands in for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarEquivalent {
+    /// The entry describes the resource of the BAR in the given slot (`0..=5`)
+    Bar(u8),
+    /// The entry describes a resource behind a PCI-to-PCI bridge
+    BehindBridge,
+    /// The entry describes the Expansion ROM
+    ExpansionRom,
+    /// The entry describes the resource of the given VF BAR (`0..=5`) of an SR-IOV capability
+    VfBar(u8),
+    /// The entry does not correspond to any BAR ("Equivalent Not Indicated")
+    None,
+    /// A reserved indicator value. The specification says these are to be handled like
+    /// [`BarEquivalent::None`].
+    Reserved(u8),
+}
+
+impl From<u8> for BarEquivalent {
+    fn from(value: u8) -> Self {
+        match value {
+            0..=5 => BarEquivalent::Bar(value),
+            6 => BarEquivalent::BehindBridge,
+            7 => BarEquivalent::None,
+            8 => BarEquivalent::ExpansionRom,
+            9..=14 => BarEquivalent::VfBar(value - 9),
+            _ => BarEquivalent::Reserved(value),
+        }
+    }
+}
+
+/// The kind of resource an Enhanced Allocation entry describes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EaProperty {
+    MemoryNonPrefetchable,
+    MemoryPrefetchable,
+    Io,
+    VfMemoryPrefetchable,
+    VfMemoryNonPrefetchable,
+    BridgeMemoryNonPrefetchable,
+    BridgeMemoryPrefetchable,
+    BridgeIo,
+    MemoryReserved,
+    IoReserved,
+    /// The entry is not available for use. As a primary property, this means the secondary property should be
+    /// used instead.
+    Unavailable,
+    Unknown(u8),
+}
+
+impl From<u8> for EaProperty {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => EaProperty::MemoryNonPrefetchable,
+            0x01 => EaProperty::MemoryPrefetchable,
+            0x02 => EaProperty::Io,
+            0x03 => EaProperty::VfMemoryPrefetchable,
+            0x04 => EaProperty::VfMemoryNonPrefetchable,
+            0x05 => EaProperty::BridgeMemoryNonPrefetchable,
+            0x06 => EaProperty::BridgeMemoryPrefetchable,
+            0x07 => EaProperty::BridgeIo,
+            0xfd => EaProperty::MemoryReserved,
+            0xfe => EaProperty::IoReserved,
+            0xff => EaProperty::Unavailable,
+            _ => EaProperty::Unknown(value),
+        }
+    }
+}
+
+/// A single resource described by the Enhanced Allocation capability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EaEntry {
+    pub bar_equivalent: BarEquivalent,
+    pub primary_property: EaProperty,
+    pub secondary_property: EaProperty,
+    /// The address the resource starts at. Unlike a BAR, this is fixed by the hardware.
+    pub base: u64,
+    /// The offset of the last byte of the resource from `base`
+    pub max_offset: u64,
+    /// Will be `true` if `base` and `max_offset` can be written by software
+    pub writable: bool,
+    pub enabled: bool,
+}
+
+impl EaEntry {
+    /// The kind of resource this entry describes, taking the secondary property into account when the primary
+    /// one is unavailable
+    pub fn property(&self) -> EaProperty {
+        match self.primary_property {
+            EaProperty::Unavailable => self.secondary_property,
+            property => property,
+        }
+    }
+
+    /// The size of the resource in bytes
+    pub fn size(&self) -> u64 {
+        self.max_offset.wrapping_add(1)
+    }
+}
+
+/// Enhanced Allocation capability, used by some devices (mostly embedded ones) to describe resources at fixed
+/// addresses instead of through BARs. Devices using it often have unimplemented BARs.
+#[derive(Debug, Clone)]
+pub struct EnhancedAllocationCapability {
+    address: PciCapabilityAddress,
+    num_entries: u8,
+}
+
+impl EnhancedAllocationCapability {
+    pub(crate) fn new(
+        address: PciCapabilityAddress,
+        extension: u16,
+    ) -> EnhancedAllocationCapability {
+        EnhancedAllocationCapability {
+            address,
+            num_entries: extension.get_bits(0..6) as u8,
+        }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// How many entries the capability has
+    #[inline]
+    pub fn num_entries(&self) -> u8 {
+        self.num_entries
+    }
+
+    /// For bridges, the fixed secondary and subordinate bus numbers of the bridge. Returns `None` for functions
+    /// which are not PCI-to-PCI bridges.
+    pub fn fixed_bus_numbers(&self, access: &impl ConfigRegionAccess) -> Option<(u8, u8)> {
+        if !self.is_bridge(access) {
+            return None;
+        }
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        Some((reg.get_bits(0..8) as u8, reg.get_bits(8..16) as u8))
+    }
+
+    /// Iterate over the entries of the capability
+    pub fn entries<'a, T: ConfigRegionAccess>(&self, access: &'a T) -> EaEntryIterator<'a, T> {
+        /*
+         * Bridges have an extra dword holding fixed bus numbers before the first entry.
+         */
+        let first_entry = if self.is_bridge(access) { 0x8 } else { 0x4 };
+        EaEntryIterator {
+            address: self.address.clone(),
+            offset: self.address.offset + first_entry,
+            remaining: self.num_entries,
+            access,
+        }
+    }
+
+    fn is_bridge(&self, access: &impl ConfigRegionAccess) -> bool {
+        PciHeader::new(self.address.address).header_type(access) == HeaderType::PciPciBridge
+    }
+}
+
+pub struct EaEntryIterator<'a, T: ConfigRegionAccess> {
+    address: PciCapabilityAddress,
+    offset: u16,
+    remaining: u8,
+    access: &'a T,
+}
+
+impl<'a, T: ConfigRegionAccess> Iterator for EaEntryIterator<'a, T> {
+    type Item = EaEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.offset >= 0x100 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let read = |offset: u16| unsafe { self.access.read(self.address.address, offset) };
+        let header = read(self.offset);
+        /*
+         * The entry size is the number of dwords following the first one, so entries can be skipped even if they
+         * have fields this crate doesn't know about.
+         */
+        let entry_size = header.get_bits(0..3) as u16;
+        let next_offset = self.offset + 4 + entry_size * 4;
+
+        let base_low = read(self.offset + 0x4);
+        let max_offset_low = read(self.offset + 0x8);
+        let mut extra = self.offset + 0xc;
+
+        let mut base = (base_low & !0x3) as u64;
+        if base_low.get_bit(1) {
+            base.set_bits(32..64, read(extra) as u64);
+            extra += 4;
+        }
+        let mut max_offset = (max_offset_low | 0x3) as u64;
+        if max_offset_low.get_bit(1) {
+            max_offset.set_bits(32..64, read(extra) as u64);
+        }
+
+        self.offset = next_offset;
+        Some(EaEntry {
+            bar_equivalent: BarEquivalent::from(header.get_bits(4..8) as u8),
+            primary_property: EaProperty::from(header.get_bits(8..16) as u8),
+            secondary_property: EaProperty::from(header.get_bits(16..24) as u8),
+            base,
+            max_offset,
+            writable: header.get_bit(30),
+            enabled: header.get_bit(31),
+        })
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{
+    capability::{validate, PciCapabilityAddress},
+    ConfigRegionAccess, PciAddress,
+};
+use bit_field::BitField;
+use core::{fmt::Formatter, ops::Range};
+
+/// PCI Express extended capability IDs, as found in the lower 16 bits of every extended capability header
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ExtendedCapabilityId {
+    /// Null extended capability, Cap ID = `0x0000`
+    Null,
+    /// Advanced error reporting capability, Cap ID = `0x0001`
+    AdvancedErrorReporting,
+    /// Virtual channel capability, Cap ID = `0x0002`
+    VirtualChannel,
+    /// Device serial number capability, Cap ID = `0x0003`
+    DeviceSerialNumber,
+    /// Power budgeting capability, Cap ID = `0x0004`
+    PowerBudgeting,
+    /// Root complex link declaration capability, Cap ID = `0x0005`
+    RootComplexLinkDeclaration,
+    /// Root complex internal link control capability, Cap ID = `0x0006`
+    RootComplexInternalLinkControl,
+    /// Root complex event collector endpoint association capability, Cap ID = `0x0007`
+    RootComplexEventCollectorEndpointAssociation,
+    /// Multi-function virtual channel capability, Cap ID = `0x0008`
+    MultiFunctionVirtualChannel,
+    /// Virtual channel capability, used when a multi-function virtual channel capability is present,
+    /// Cap ID = `0x0009`
+    VirtualChannelMfvc,
+    /// Root complex register block capability, Cap ID = `0x000A`
+    RootComplexRegisterBlock,
+    /// Vendor-specific extended capability, Cap ID = `0x000B`
+    VendorSpecific,
+    /// Access control services capability, Cap ID = `0x000D`
+    AccessControlServices,
+    /// Alternative routing-ID interpretation capability, Cap ID = `0x000E`
+    AlternativeRoutingId,
+    /// Address translation services capability, Cap ID = `0x000F`
+    AddressTranslationServices,
+    /// Single root I/O virtualization capability, Cap ID = `0x0010`
+    SingleRootIov,
+    /// Multi-root I/O virtualization capability, Cap ID = `0x0011`
+    MultiRootIov,
+    /// Multicast capability, Cap ID = `0x0012`
+    Multicast,
+    /// Page request interface capability, Cap ID = `0x0013`
+    PageRequest,
+    /// Resizable BAR capability, Cap ID = `0x0015`
+    ResizableBar,
+    /// Dynamic power allocation capability, Cap ID = `0x0016`
+    DynamicPowerAllocation,
+    /// TPH requester capability, Cap ID = `0x0017`
+    TphRequester,
+    /// Latency tolerance reporting capability, Cap ID = `0x0018`
+    LatencyToleranceReporting,
+    /// Secondary PCI Express capability, Cap ID = `0x0019`
+    SecondaryPciExpress,
+    /// Process address space ID capability, Cap ID = `0x001B`
+    ProcessAddressSpaceId,
+    /// Downstream port containment capability, Cap ID = `0x001D`
+    DownstreamPortContainment,
+    /// L1 PM substates capability, Cap ID = `0x001E`
+    L1PmSubstates,
+    /// Precision time measurement capability, Cap ID = `0x001F`
+    PrecisionTimeMeasurement,
+    /// Designated vendor-specific extended capability, Cap ID = `0x0023`
+    DesignatedVendorSpecific,
+    /// Data link feature capability, Cap ID = `0x0025`
+    DataLinkFeature,
+    /// Physical layer 16.0 GT/s capability, Cap ID = `0x0026`
+    PhysicalLayer16,
+    /// Extended capability ID not known to this crate
+    Unknown(u16),
+}
+
+impl From<u16> for ExtendedCapabilityId {
+    fn from(id: u16) -> Self {
+        match id {
+            0x0000 => ExtendedCapabilityId::Null,
+            0x0001 => ExtendedCapabilityId::AdvancedErrorReporting,
+            0x0002 => ExtendedCapabilityId::VirtualChannel,
+            0x0003 => ExtendedCapabilityId::DeviceSerialNumber,
+            0x0004 => ExtendedCapabilityId::PowerBudgeting,
+            0x0005 => ExtendedCapabilityId::RootComplexLinkDeclaration,
+            0x0006 => ExtendedCapabilityId::RootComplexInternalLinkControl,
+            0x0007 => ExtendedCapabilityId::RootComplexEventCollectorEndpointAssociation,
+            0x0008 => ExtendedCapabilityId::MultiFunctionVirtualChannel,
+            0x0009 => ExtendedCapabilityId::VirtualChannelMfvc,
+            0x000A => ExtendedCapabilityId::RootComplexRegisterBlock,
+            0x000B => ExtendedCapabilityId::VendorSpecific,
+            0x000D => ExtendedCapabilityId::AccessControlServices,
+            0x000E => ExtendedCapabilityId::AlternativeRoutingId,
+            0x000F => ExtendedCapabilityId::AddressTranslationServices,
+            0x0010 => ExtendedCapabilityId::SingleRootIov,
+            0x0011 => ExtendedCapabilityId::MultiRootIov,
+            0x0012 => ExtendedCapabilityId::Multicast,
+            0x0013 => ExtendedCapabilityId::PageRequest,
+            0x0015 => ExtendedCapabilityId::ResizableBar,
+            0x0016 => ExtendedCapabilityId::DynamicPowerAllocation,
+            0x0017 => ExtendedCapabilityId::TphRequester,
+            0x0018 => ExtendedCapabilityId::LatencyToleranceReporting,
+            0x0019 => ExtendedCapabilityId::SecondaryPciExpress,
+            0x001B => ExtendedCapabilityId::ProcessAddressSpaceId,
+            0x001D => ExtendedCapabilityId::DownstreamPortContainment,
+            0x001E => ExtendedCapabilityId::L1PmSubstates,
+            0x001F => ExtendedCapabilityId::PrecisionTimeMeasurement,
+            0x0023 => ExtendedCapabilityId::DesignatedVendorSpecific,
+            0x0025 => ExtendedCapabilityId::DataLinkFeature,
+            0x0026 => ExtendedCapabilityId::PhysicalLayer16,
+            id => ExtendedCapabilityId::Unknown(id),
+        }
+    }
+}
+
+impl From<ExtendedCapabilityId> for u16 {
+    fn from(id: ExtendedCapabilityId) -> Self {
+        match id {
+            ExtendedCapabilityId::Null => 0x0000,
+            ExtendedCapabilityId::AdvancedErrorReporting => 0x0001,
+            ExtendedCapabilityId::VirtualChannel => 0x0002,
+            ExtendedCapabilityId::DeviceSerialNumber => 0x0003,
+            ExtendedCapabilityId::PowerBudgeting => 0x0004,
+            ExtendedCapabilityId::RootComplexLinkDeclaration => 0x0005,
+            ExtendedCapabilityId::RootComplexInternalLinkControl => 0x0006,
+            ExtendedCapabilityId::RootComplexEventCollectorEndpointAssociation => 0x0007,
+            ExtendedCapabilityId::MultiFunctionVirtualChannel => 0x0008,
+            ExtendedCapabilityId::VirtualChannelMfvc => 0x0009,
+            ExtendedCapabilityId::RootComplexRegisterBlock => 0x000A,
+            ExtendedCapabilityId::VendorSpecific => 0x000B,
+            ExtendedCapabilityId::AccessControlServices => 0x000D,
+            ExtendedCapabilityId::AlternativeRoutingId => 0x000E,
+            ExtendedCapabilityId::AddressTranslationServices => 0x000F,
+            ExtendedCapabilityId::SingleRootIov => 0x0010,
+            ExtendedCapabilityId::MultiRootIov => 0x0011,
+            ExtendedCapabilityId::Multicast => 0x0012,
+            ExtendedCapabilityId::PageRequest => 0x0013,
+            ExtendedCapabilityId::ResizableBar => 0x0015,
+            ExtendedCapabilityId::DynamicPowerAllocation => 0x0016,
+            ExtendedCapabilityId::TphRequester => 0x0017,
+            ExtendedCapabilityId::LatencyToleranceReporting => 0x0018,
+            ExtendedCapabilityId::SecondaryPciExpress => 0x0019,
+            ExtendedCapabilityId::ProcessAddressSpaceId => 0x001B,
+            ExtendedCapabilityId::DownstreamPortContainment => 0x001D,
+            ExtendedCapabilityId::L1PmSubstates => 0x001E,
+            ExtendedCapabilityId::PrecisionTimeMeasurement => 0x001F,
+            ExtendedCapabilityId::DesignatedVendorSpecific => 0x0023,
+            ExtendedCapabilityId::DataLinkFeature => 0x0025,
+            ExtendedCapabilityId::PhysicalLayer16 => 0x0026,
+            ExtendedCapabilityId::Unknown(id) => id,
+        }
+    }
+}
+
+impl core::fmt::Display for ExtendedCapabilityId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ExtendedCapabilityId::Null => "Null",
+            ExtendedCapabilityId::AdvancedErrorReporting => "Advanced Error Reporting",
+            ExtendedCapabilityId::VirtualChannel => "Virtual Channel",
+            ExtendedCapabilityId::DeviceSerialNumber => "Device Serial Number",
+            ExtendedCapabilityId::PowerBudgeting => "Power Budgeting",
+            ExtendedCapabilityId::RootComplexLinkDeclaration => "Root Complex Link Declaration",
+            ExtendedCapabilityId::RootComplexInternalLinkControl => {
+                "Root Complex Internal Link Control"
+            }
+            ExtendedCapabilityId::RootComplexEventCollectorEndpointAssociation => {
+                "Root Complex Event Collector Endpoint Association"
+            }
+            ExtendedCapabilityId::MultiFunctionVirtualChannel => "Multi-Function Virtual Channel",
+            ExtendedCapabilityId::VirtualChannelMfvc => "Virtual Channel (MFVC)",
+            ExtendedCapabilityId::RootComplexRegisterBlock => "Root Complex Register Block",
+            ExtendedCapabilityId::VendorSpecific => "Vendor Specific",
+            ExtendedCapabilityId::AccessControlServices => "Access Control Services",
+            ExtendedCapabilityId::AlternativeRoutingId => "Alternative Routing-ID Interpretation",
+            ExtendedCapabilityId::AddressTranslationServices => "Address Translation Services",
+            ExtendedCapabilityId::SingleRootIov => "Single Root I/O Virtualization",
+            ExtendedCapabilityId::MultiRootIov => "Multi-Root I/O Virtualization",
+            ExtendedCapabilityId::Multicast => "Multicast",
+            ExtendedCapabilityId::PageRequest => "Page Request Interface",
+            ExtendedCapabilityId::ResizableBar => "Resizable BAR",
+            ExtendedCapabilityId::DynamicPowerAllocation => "Dynamic Power Allocation",
+            ExtendedCapabilityId::TphRequester => "TPH Requester",
+            ExtendedCapabilityId::LatencyToleranceReporting => "Latency Tolerance Reporting",
+            ExtendedCapabilityId::SecondaryPciExpress => "Secondary PCI Express",
+            ExtendedCapabilityId::ProcessAddressSpaceId => "Process Address Space ID",
+            ExtendedCapabilityId::DownstreamPortContainment => "Downstream Port Containment",
+            ExtendedCapabilityId::L1PmSubstates => "L1 PM Substates",
+            ExtendedCapabilityId::PrecisionTimeMeasurement => "Precision Time Measurement",
+            ExtendedCapabilityId::DesignatedVendorSpecific => "Designated Vendor-Specific",
+            ExtendedCapabilityId::DataLinkFeature => "Data Link Feature",
+            ExtendedCapabilityId::PhysicalLayer16 => "Physical Layer 16.0 GT/s",
+            ExtendedCapabilityId::Unknown(id) => return write!(f, "Unknown ({:#06x})", id),
+        };
+        f.write_str(name)
+    }
+}
+
+/// PCI Express extended capability, found in the extended configuration space (offsets `0x100..0x1000`)
+#[derive(Clone, Debug)]
+pub struct PciExtendedCapability {
+    address: PciCapabilityAddress,
+    id: ExtendedCapabilityId,
+    version: u8,
+}
+
+impl PciExtendedCapability {
+    /// Location of this capability's header in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// The ID of this capability
+    #[inline]
+    pub fn id(&self) -> ExtendedCapabilityId {
+        self.id
+    }
+
+    /// The version of this capability's structure
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The bytes of configuration space taken up by this capability, including its header. Like
+    /// [`crate::capability::PciCapability::byte_range`], this only covers the registers the capability must
+    /// have.
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.address.offset as usize;
+        start..start + validate::extended_length(self.id) as usize
+    }
+
+    /// Read the header of this capability. See [`PciCapabilityAddress::header_dword`].
+    pub fn header_dword(&self, access: &impl ConfigRegionAccess) -> u32 {
+        self.address.header_dword(access)
+    }
+
+    /// Read the dwords of this capability covered by [`PciExtendedCapability::byte_range`], starting with the
+    /// header. This gives raw access to capabilities this crate doesn't decode (e.g. Multicast, TPH Requester,
+    /// or the status registers of the Data Link Feature and Physical Layer 16.0 GT/s capabilities).
+    pub fn dwords<'a>(
+        &self,
+        access: &'a impl ConfigRegionAccess,
+    ) -> impl Iterator<Item = u32> + 'a {
+        let address = self.address.address;
+        self.byte_range()
+            .step_by(4)
+            .map(move |offset| unsafe { access.read(address, offset as u16) })
+    }
+}
+
+pub struct ExtendedCapabilityIterator<'a, T: ConfigRegionAccess> {
+    address: PciAddress,
+    offset: u16,
+    remaining: u16,
+    access: &'a T,
+}
+
+impl<'a, T: ConfigRegionAccess> ExtendedCapabilityIterator<'a, T> {
+    pub(crate) fn new(
+        address: PciAddress,
+        offset: u16,
+        access: &'a T,
+    ) -> ExtendedCapabilityIterator<'a, T> {
+        ExtendedCapabilityIterator {
+            address,
+            offset,
+            /*
+             * Every capability takes up at least one dword, so a well-formed list can't be longer than this. This
+             * stops us from looping forever on a malformed list that links back on itself. Accesses that can't
+             * reach the extended configuration space would read garbage there, so they get an empty list.
+             */
+            remaining: if access.supports_extended() {
+                (0x1000 - 0x100) / 4
+            } else {
+                0
+            },
+            access,
+        }
+    }
+}
+
+impl<'a, T: ConfigRegionAccess> Iterator for ExtendedCapabilityIterator<'a, T> {
+    type Item = PciExtendedCapability;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.offset < 0x100 || self.offset >= 0x1000 || self.remaining == 0 {
+                return None;
+            }
+            self.remaining -= 1;
+
+            let data = unsafe { self.access.read(self.address, self.offset) };
+            /*
+             * A header of all zeroes means there are no extended capabilities, and all ones means the function
+             * doesn't have an extended configuration space at all.
+             */
+            if data == 0 || data == 0xffffffff {
+                return None;
+            }
+
+            let id = ExtendedCapabilityId::from(data.get_bits(0..16) as u16);
+            let version = data.get_bits(16..20) as u8;
+            let address = PciCapabilityAddress {
+                address: self.address,
+                offset: self.offset,
+            };
+            self.offset = (data.get_bits(20..32) as u16) & !0x3;
+
+            if id != ExtendedCapabilityId::Null {
+                return Some(PciExtendedCapability {
+                    address,
+                    id,
+                    version,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use bit_field::BitField;
+
+/// The largest latency that can be encoded, in nanoseconds: the maximum 10-bit value at the largest scale
+pub const MAX_LTR_LATENCY_NS: u64 = 1023 << (5 * 5);
+
+/// A latency as encoded in the LTR registers: a 10-bit value multiplied by `32^scale` nanoseconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LtrLatency {
+    value: u16,
+    scale: u8,
+}
+
+impl LtrLatency {
+    /// Encode a latency in nanoseconds, rounding up to the next latency that can be represented. Returns `None` if
+    /// it is larger than [`MAX_LTR_LATENCY_NS`].
+    pub fn from_nanos(nanos: u64) -> Option<LtrLatency> {
+        (0..=5).find_map(|scale| {
+            let unit = 1u64 << (5 * scale);
+            let value = nanos.div_ceil(unit);
+            (value < 1 << 10).then_some(LtrLatency {
+                value: value as u16,
+                scale,
+            })
+        })
+    }
+
+    /// The latency in nanoseconds. Scales reserved by the specification decode as `0`.
+    pub fn as_nanos(&self) -> u64 {
+        if self.scale > 5 {
+            return 0;
+        }
+        (self.value as u64) << (5 * self.scale)
+    }
+
+    fn from_bits(bits: u16) -> LtrLatency {
+        LtrLatency {
+            value: bits.get_bits(0..10),
+            scale: bits.get_bits(10..13) as u8,
+        }
+    }
+
+    fn bits(&self) -> u16 {
+        let mut bits = 0;
+        bits.set_bits(0..10, self.value);
+        bits.set_bits(10..13, self.scale as u16);
+        bits
+    }
+}
+
+/// Latency Tolerance Reporting extended capability, holding the largest latencies that the platform allows an
+/// upstream port to report. Only present on upstream ports.
+#[derive(Debug, Clone)]
+pub struct LtrCapability {
+    address: PciCapabilityAddress,
+}
+
+impl LtrCapability {
+    /// Interpret an extended capability as a Latency Tolerance Reporting capability. Returns `None` if it is a
+    /// different capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<LtrCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::LatencyToleranceReporting => Some(LtrCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn max_snoop_latency(&self, access: &impl ConfigRegionAccess) -> LtrLatency {
+        LtrLatency::from_bits(self.read(access).get_bits(0..16) as u16)
+    }
+
+    pub fn set_max_snoop_latency(&self, access: &impl ConfigRegionAccess, latency: LtrLatency) {
+        let mut reg = self.read(access);
+        reg.set_bits(0..16, latency.bits() as u32);
+        self.write(access, reg);
+    }
+
+    pub fn max_no_snoop_latency(&self, access: &impl ConfigRegionAccess) -> LtrLatency {
+        LtrLatency::from_bits(self.read(access).get_bits(16..32) as u16)
+    }
+
+    pub fn set_max_no_snoop_latency(&self, access: &impl ConfigRegionAccess, latency: LtrLatency) {
+        let mut reg = self.read(access);
+        reg.set_bits(16..32, latency.bits() as u32);
+        self.write(access, reg);
+    }
+
+    fn read(&self, access: &impl ConfigRegionAccess) -> u32 {
+        unsafe { access.read(self.address.address, self.address.offset + 0x4) }
+    }
+
+    fn write(&self, access: &impl ConfigRegionAccess, value: u32) {
+        unsafe { access.write(self.address.address, self.address.offset + 0x4, value) }
+    }
+}
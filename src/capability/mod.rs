@@ -1,10 +1,53 @@
-use crate::{ConfigRegionAccess, PciAddress};
+use crate::{ConfigRegionAccess, HeaderType, PciAddress, PciHeader};
 use bit_field::BitField;
-use core::fmt::Formatter;
+use core::{fmt::Formatter, ops::Range};
 
+mod acs;
+mod aer;
+mod af;
+mod ari;
+mod dsn;
+mod ea;
+mod extended;
+mod ltr;
 mod msi;
+mod pcie;
+mod pcix;
+mod pm;
+mod rebar;
+#[cfg(feature = "alloc")]
+mod snapshot;
+mod subsystem;
+mod validate;
+mod visit;
+mod vpd;
+mod vsec;
 
+pub use acs::{AcsCapability, AcsError, AcsFeatures};
+pub use aer::{AdvancedErrorReportingCapability, CorrectableErrors, UncorrectableErrors};
+pub use af::{AdvancedFeaturesCapability, AdvancedFeaturesError};
+pub use ari::AriCapability;
+pub use dsn::DeviceSerialNumberCapability;
+pub use ea::{BarEquivalent, EaEntry, EaEntryIterator, EaProperty, EnhancedAllocationCapability};
+pub use extended::{ExtendedCapabilityId, ExtendedCapabilityIterator, PciExtendedCapability};
+pub use ltr::{LtrCapability, LtrLatency, MAX_LTR_LATENCY_NS};
 pub use msi::{MsiCapability, MultipleMessageSupport, TriggerMode};
+pub use pcie::{
+    AspmControl, DeviceCapabilities, DeviceControl2, IndicatorState, LinkCapabilities, LinkControl,
+    ObffEnable, PcieCapability, PcieError, PciePortType, SlotCapabilities, SlotControl, SlotStatus,
+};
+pub use pcix::{PciXCapability, PciXCommand, PciXStatus};
+pub use pm::{PowerManagementCapability, PowerState};
+pub use rebar::{ResizableBarCapability, ResizableBarEntry, ResizableBarError};
+#[cfg(feature = "alloc")]
+pub use snapshot::{CapabilityChange, CapabilityEntry, CapabilityKind, CapabilitySnapshot};
+pub use subsystem::BridgeSubsystemCapability;
+pub use validate::{
+    parse_capabilities, validate_capabilities, CapabilityError, CapabilityParseError,
+};
+pub use visit::{walk_capabilities, CapabilityVisitor};
+pub use vpd::VpdCapability;
+pub use vsec::VsecCapability;
 
 #[derive(Clone)]
 pub struct PciCapabilityAddress {
@@ -12,6 +55,16 @@ pub struct PciCapabilityAddress {
     pub offset: u16,
 }
 
+impl PciCapabilityAddress {
+    /// Read the first dword of the capability. For standard capabilities this holds the ID, the next pointer and
+    /// the capability-specific upper half (often a control register), and for extended capabilities the ID,
+    /// version and next pointer. Every capability type exposes its address, so this gives the same raw view of
+    /// any of them, e.g. for tracing.
+    pub fn header_dword(&self, access: &impl ConfigRegionAccess) -> u32 {
+        unsafe { access.read(self.address, self.offset) }
+    }
+}
+
 impl core::fmt::Debug for PciCapabilityAddress {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}, offset: {:02x}", self.address, self.offset)
@@ -19,14 +72,18 @@ impl core::fmt::Debug for PciCapabilityAddress {
 }
 
 /// PCI capabilities
+///
+/// Capabilities are per-function, but some multifunction devices only expose capabilities that affect the whole
+/// device in function 0, and leave them out of the lists of their other functions. If a function is missing a
+/// capability it is expected to have, check the list of [`crate::PciAddress::function_zero`] too.
 #[derive(Clone, Debug)]
 pub enum PciCapability {
     /// Power management capability, Cap ID = `0x01`
-    PowerManagement(PciCapabilityAddress),
+    PowerManagement(PowerManagementCapability),
     /// Accelerated graphics port capability, Cap ID = `0x02`
     AcceleratedGraphicsPort(PciCapabilityAddress),
     /// Vital product data capability, Cap ID = `0x3`
-    VitalProductData(PciCapabilityAddress),
+    VitalProductData(VpdCapability),
     /// Slot identification capability, Cap ID = `0x04`
     SlotIdentification(PciCapabilityAddress),
     /// Message signalling interrupts capability, Cap ID = `0x05`
@@ -34,7 +91,7 @@ pub enum PciCapability {
     /// CompactPCI HotSwap capability, Cap ID = `0x06`
     CompactPCIHotswap(PciCapabilityAddress),
     /// PCI-X capability, Cap ID = `0x07`
-    PciX(PciCapabilityAddress),
+    PciX(PciXCapability),
     /// HyperTransport capability, Cap ID = `0x08`
     HyperTransport(PciCapabilityAddress),
     /// Vendor-specific capability, Cap ID = `0x09`
@@ -46,13 +103,17 @@ pub enum PciCapability {
     /// PCI Standard Hot-Plug Controller capability, Cap ID = `0x0C`
     PciHotPlugControl(PciCapabilityAddress),
     /// Bridge subsystem vendor/device ID capability, Cap ID = `0x0D`
-    BridgeSubsystemVendorId(PciCapabilityAddress),
+    BridgeSubsystemVendorId(BridgeSubsystemCapability),
     /// AGP Target PCI-PCI bridge capability, Cap ID = `0x0E`
     AGP3(PciCapabilityAddress),
     /// PCI Express capability, Cap ID = `0x10`
-    PciExpress(PciCapabilityAddress),
+    PciExpress(PcieCapability),
     /// MSI-X capability, Cap ID = `0x11`
     MsiX(PciCapabilityAddress),
+    /// Advanced features capability, Cap ID = `0x13`
+    AdvancedFeatures(AdvancedFeaturesCapability),
+    /// Enhanced allocation capability, Cap ID = `0x14`
+    EnhancedAllocation(EnhancedAllocationCapability),
     /// Unknown capability
     Unknown {
         address: PciCapabilityAddress,
@@ -60,38 +121,331 @@ pub enum PciCapability {
     },
 }
 
+/// Standard PCI capability IDs, as found in the first byte of every capability in the capability list
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum CapabilityId {
+    /// Null capability, Cap ID = `0x00`
+    Null,
+    /// Power management capability, Cap ID = `0x01`
+    PowerManagement,
+    /// Accelerated graphics port capability, Cap ID = `0x02`
+    AcceleratedGraphicsPort,
+    /// Vital product data capability, Cap ID = `0x03`
+    VitalProductData,
+    /// Slot identification capability, Cap ID = `0x04`
+    SlotIdentification,
+    /// Message signalling interrupts capability, Cap ID = `0x05`
+    Msi,
+    /// CompactPCI HotSwap capability, Cap ID = `0x06`
+    CompactPCIHotswap,
+    /// PCI-X capability, Cap ID = `0x07`
+    PciX,
+    /// HyperTransport capability, Cap ID = `0x08`
+    HyperTransport,
+    /// Vendor-specific capability, Cap ID = `0x09`
+    VendorSpecific,
+    /// Debug port capability, Cap ID = `0x0A`
+    DebugPort,
+    /// CompactPCI Central Resource Control capability, Cap ID = `0x0B`
+    CompactPCICentralResourceControl,
+    /// PCI Standard Hot-Plug Controller capability, Cap ID = `0x0C`
+    PciHotPlugControl,
+    /// Bridge subsystem vendor/device ID capability, Cap ID = `0x0D`
+    BridgeSubsystemVendorId,
+    /// AGP Target PCI-PCI bridge capability, Cap ID = `0x0E`
+    AGP3,
+    /// Secure device capability, Cap ID = `0x0F`
+    SecureDevice,
+    /// PCI Express capability, Cap ID = `0x10`
+    PciExpress,
+    /// MSI-X capability, Cap ID = `0x11`
+    MsiX,
+    /// Serial ATA Data/Index Configuration capability, Cap ID = `0x12`
+    SataConfiguration,
+    /// Advanced features capability, Cap ID = `0x13`
+    AdvancedFeatures,
+    /// Enhanced allocation capability, Cap ID = `0x14`
+    EnhancedAllocation,
+    /// Flattening portal bridge capability, Cap ID = `0x15`
+    FlatteningPortalBridge,
+    /// Capability ID not known to this crate
+    Unknown(u8),
+}
+
+impl From<u8> for CapabilityId {
+    fn from(id: u8) -> Self {
+        match id {
+            0x00 => CapabilityId::Null,
+            0x01 => CapabilityId::PowerManagement,
+            0x02 => CapabilityId::AcceleratedGraphicsPort,
+            0x03 => CapabilityId::VitalProductData,
+            0x04 => CapabilityId::SlotIdentification,
+            0x05 => CapabilityId::Msi,
+            0x06 => CapabilityId::CompactPCIHotswap,
+            0x07 => CapabilityId::PciX,
+            0x08 => CapabilityId::HyperTransport,
+            0x09 => CapabilityId::VendorSpecific,
+            0x0A => CapabilityId::DebugPort,
+            0x0B => CapabilityId::CompactPCICentralResourceControl,
+            0x0C => CapabilityId::PciHotPlugControl,
+            0x0D => CapabilityId::BridgeSubsystemVendorId,
+            0x0E => CapabilityId::AGP3,
+            0x0F => CapabilityId::SecureDevice,
+            0x10 => CapabilityId::PciExpress,
+            0x11 => CapabilityId::MsiX,
+            0x12 => CapabilityId::SataConfiguration,
+            0x13 => CapabilityId::AdvancedFeatures,
+            0x14 => CapabilityId::EnhancedAllocation,
+            0x15 => CapabilityId::FlatteningPortalBridge,
+            id => CapabilityId::Unknown(id),
+        }
+    }
+}
+
+impl From<CapabilityId> for u8 {
+    fn from(id: CapabilityId) -> Self {
+        match id {
+            CapabilityId::Null => 0x00,
+            CapabilityId::PowerManagement => 0x01,
+            CapabilityId::AcceleratedGraphicsPort => 0x02,
+            CapabilityId::VitalProductData => 0x03,
+            CapabilityId::SlotIdentification => 0x04,
+            CapabilityId::Msi => 0x05,
+            CapabilityId::CompactPCIHotswap => 0x06,
+            CapabilityId::PciX => 0x07,
+            CapabilityId::HyperTransport => 0x08,
+            CapabilityId::VendorSpecific => 0x09,
+            CapabilityId::DebugPort => 0x0A,
+            CapabilityId::CompactPCICentralResourceControl => 0x0B,
+            CapabilityId::PciHotPlugControl => 0x0C,
+            CapabilityId::BridgeSubsystemVendorId => 0x0D,
+            CapabilityId::AGP3 => 0x0E,
+            CapabilityId::SecureDevice => 0x0F,
+            CapabilityId::PciExpress => 0x10,
+            CapabilityId::MsiX => 0x11,
+            CapabilityId::SataConfiguration => 0x12,
+            CapabilityId::AdvancedFeatures => 0x13,
+            CapabilityId::EnhancedAllocation => 0x14,
+            CapabilityId::FlatteningPortalBridge => 0x15,
+            CapabilityId::Unknown(id) => id,
+        }
+    }
+}
+
+impl core::fmt::Display for CapabilityId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            CapabilityId::Null => "Null",
+            CapabilityId::PowerManagement => "Power Management",
+            CapabilityId::AcceleratedGraphicsPort => "AGP",
+            CapabilityId::VitalProductData => "Vital Product Data",
+            CapabilityId::SlotIdentification => "Slot Identification",
+            CapabilityId::Msi => "MSI",
+            CapabilityId::CompactPCIHotswap => "CompactPCI Hot Swap",
+            CapabilityId::PciX => "PCI-X",
+            CapabilityId::HyperTransport => "HyperTransport",
+            CapabilityId::VendorSpecific => "Vendor Specific",
+            CapabilityId::DebugPort => "Debug Port",
+            CapabilityId::CompactPCICentralResourceControl => "CompactPCI Central Resource Control",
+            CapabilityId::PciHotPlugControl => "PCI Hot-Plug",
+            CapabilityId::BridgeSubsystemVendorId => "Bridge Subsystem Vendor ID",
+            CapabilityId::AGP3 => "AGP 8x",
+            CapabilityId::SecureDevice => "Secure Device",
+            CapabilityId::PciExpress => "PCI Express",
+            CapabilityId::MsiX => "MSI-X",
+            CapabilityId::SataConfiguration => "SATA Configuration",
+            CapabilityId::AdvancedFeatures => "Advanced Features",
+            CapabilityId::EnhancedAllocation => "Enhanced Allocation",
+            CapabilityId::FlatteningPortalBridge => "Flattening Portal Bridge",
+            CapabilityId::Unknown(id) => return write!(f, "Unknown ({:#04x})", id),
+        };
+        f.write_str(name)
+    }
+}
+
+/// The offset of a capability in the 256-byte PCI configuration space. Capabilities are dword-aligned and come
+/// after the header, so only such offsets can be represented.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(transparent)]
+pub struct CapabilityOffset(u8);
+
+impl CapabilityOffset {
+    /// Returns `None` if `offset` is not dword-aligned or lies inside the header (below `0x40`)
+    pub const fn new(offset: u8) -> Option<CapabilityOffset> {
+        if offset >= 0x40 && offset.is_multiple_of(4) {
+            Some(CapabilityOffset(offset))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(&self) -> u8 {
+        self.0
+    }
+}
+
 impl PciCapability {
+    /// Parse the capability at `offset`, without walking the capability list. Returns `None` for a Null
+    /// capability.
+    pub(crate) fn read_at(
+        address: PciAddress,
+        offset: CapabilityOffset,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<PciCapability> {
+        let offset = offset.get() as u16;
+        let data = unsafe { access.read(address, offset) };
+        PciCapability::parse(
+            data.get_bits(0..8) as u8,
+            PciCapabilityAddress { address, offset },
+            data.get_bits(16..32) as u16,
+        )
+    }
+
     fn parse(id: u8, address: PciCapabilityAddress, extension: u16) -> Option<PciCapability> {
-        match id {
-            0x00 => None, // null capability
-            0x01 => Some(PciCapability::PowerManagement(address)),
-            0x02 => Some(PciCapability::AcceleratedGraphicsPort(address)),
-            0x03 => Some(PciCapability::VitalProductData(address)),
-            0x04 => Some(PciCapability::SlotIdentification(address)),
-            0x05 => Some(PciCapability::Msi(MsiCapability::new(address, extension))),
-            0x06 => Some(PciCapability::CompactPCIHotswap(address)),
-            0x07 => Some(PciCapability::PciX(address)),
-            0x08 => Some(PciCapability::HyperTransport(address)),
-            0x09 => Some(PciCapability::Vendor(address)),
-            0x0A => Some(PciCapability::DebugPort(address)),
-            0x0B => Some(PciCapability::CompactPCICentralResourceControl(address)),
-            0x0C => Some(PciCapability::PciHotPlugControl(address)),
-            0x0D => Some(PciCapability::BridgeSubsystemVendorId(address)),
-            0x0E => Some(PciCapability::AGP3(address)),
-            0x10 => Some(PciCapability::PciExpress(address)),
-            0x11 => Some(PciCapability::MsiX(address)),
+        match CapabilityId::from(id) {
+            CapabilityId::Null => None,
+            CapabilityId::PowerManagement => Some(PciCapability::PowerManagement(
+                PowerManagementCapability::new(address, extension),
+            )),
+            CapabilityId::AcceleratedGraphicsPort => {
+                Some(PciCapability::AcceleratedGraphicsPort(address))
+            }
+            CapabilityId::VitalProductData => {
+                Some(PciCapability::VitalProductData(VpdCapability::new(address)))
+            }
+            CapabilityId::SlotIdentification => Some(PciCapability::SlotIdentification(address)),
+            CapabilityId::Msi => Some(PciCapability::Msi(MsiCapability::new(address, extension))),
+            CapabilityId::CompactPCIHotswap => Some(PciCapability::CompactPCIHotswap(address)),
+            CapabilityId::PciX => Some(PciCapability::PciX(PciXCapability::new(address))),
+            CapabilityId::HyperTransport => Some(PciCapability::HyperTransport(address)),
+            CapabilityId::VendorSpecific => Some(PciCapability::Vendor(address)),
+            CapabilityId::DebugPort => Some(PciCapability::DebugPort(address)),
+            CapabilityId::CompactPCICentralResourceControl => {
+                Some(PciCapability::CompactPCICentralResourceControl(address))
+            }
+            CapabilityId::PciHotPlugControl => Some(PciCapability::PciHotPlugControl(address)),
+            CapabilityId::BridgeSubsystemVendorId => Some(PciCapability::BridgeSubsystemVendorId(
+                BridgeSubsystemCapability::new(address),
+            )),
+            CapabilityId::AGP3 => Some(PciCapability::AGP3(address)),
+            CapabilityId::PciExpress => Some(PciCapability::PciExpress(PcieCapability::new(
+                address, extension,
+            ))),
+            CapabilityId::MsiX => Some(PciCapability::MsiX(address)),
+            CapabilityId::AdvancedFeatures => Some(PciCapability::AdvancedFeatures(
+                AdvancedFeaturesCapability::new(address, extension),
+            )),
+            CapabilityId::EnhancedAllocation => Some(PciCapability::EnhancedAllocation(
+                EnhancedAllocationCapability::new(address, extension),
+            )),
             _ => Some(PciCapability::Unknown { address, id }),
         }
     }
+
+    /// Location of this capability in configuration space
+    pub fn address(&self) -> &PciCapabilityAddress {
+        match self {
+            PciCapability::PowerManagement(capability) => capability.address(),
+            PciCapability::VitalProductData(capability) => capability.address(),
+            PciCapability::Msi(capability) => capability.address(),
+            PciCapability::PciX(capability) => capability.address(),
+            PciCapability::BridgeSubsystemVendorId(capability) => capability.address(),
+            PciCapability::PciExpress(capability) => capability.address(),
+            PciCapability::AdvancedFeatures(capability) => capability.address(),
+            PciCapability::EnhancedAllocation(capability) => capability.address(),
+            PciCapability::AcceleratedGraphicsPort(address)
+            | PciCapability::SlotIdentification(address)
+            | PciCapability::CompactPCIHotswap(address)
+            | PciCapability::HyperTransport(address)
+            | PciCapability::Vendor(address)
+            | PciCapability::DebugPort(address)
+            | PciCapability::CompactPCICentralResourceControl(address)
+            | PciCapability::PciHotPlugControl(address)
+            | PciCapability::AGP3(address)
+            | PciCapability::MsiX(address)
+            | PciCapability::Unknown { address, .. } => address,
+        }
+    }
+
+    /// Read the first dword of this capability. See [`PciCapabilityAddress::header_dword`].
+    pub fn header_dword(&self, access: &impl ConfigRegionAccess) -> u32 {
+        self.address().header_dword(access)
+    }
+
+    /// The bytes of configuration space taken up by this capability, including its header. The length is the
+    /// minimum the capability must have to hold all of its registers, so it may be shorter than the space the
+    /// device actually reserved for it.
+    pub fn byte_range(&self, access: &impl ConfigRegionAccess) -> Range<usize> {
+        let header = self.header_dword(access);
+        let start = self.address().offset as usize;
+        start..start + validate::standard_length(self.id(), header) as usize
+    }
+
+    /// Read the dwords of this capability, from its header to the end of [`PciCapability::byte_range`]. A
+    /// capability whose length isn't a multiple of 4 ends partway through its last dword, which is still read
+    /// whole. See [`PciExtendedCapability::dwords`] for extended capabilities.
+    pub fn dwords<'a>(
+        &self,
+        access: &'a impl ConfigRegionAccess,
+    ) -> impl Iterator<Item = u32> + 'a {
+        let address = self.address().address;
+        let range = self.byte_range(access);
+        (range.start..range.end.div_ceil(4) * 4)
+            .step_by(4)
+            .map(move |offset| unsafe { access.read(address, offset as u16) })
+    }
+
+    /// The ID of this capability
+    pub fn id(&self) -> CapabilityId {
+        match self {
+            PciCapability::PowerManagement(_) => CapabilityId::PowerManagement,
+            PciCapability::AcceleratedGraphicsPort(_) => CapabilityId::AcceleratedGraphicsPort,
+            PciCapability::VitalProductData(_) => CapabilityId::VitalProductData,
+            PciCapability::SlotIdentification(_) => CapabilityId::SlotIdentification,
+            PciCapability::Msi(_) => CapabilityId::Msi,
+            PciCapability::CompactPCIHotswap(_) => CapabilityId::CompactPCIHotswap,
+            PciCapability::PciX(_) => CapabilityId::PciX,
+            PciCapability::HyperTransport(_) => CapabilityId::HyperTransport,
+            PciCapability::Vendor(_) => CapabilityId::VendorSpecific,
+            PciCapability::DebugPort(_) => CapabilityId::DebugPort,
+            PciCapability::CompactPCICentralResourceControl(_) => {
+                CapabilityId::CompactPCICentralResourceControl
+            }
+            PciCapability::PciHotPlugControl(_) => CapabilityId::PciHotPlugControl,
+            PciCapability::BridgeSubsystemVendorId(_) => CapabilityId::BridgeSubsystemVendorId,
+            PciCapability::AGP3(_) => CapabilityId::AGP3,
+            PciCapability::PciExpress(_) => CapabilityId::PciExpress,
+            PciCapability::MsiX(_) => CapabilityId::MsiX,
+            PciCapability::AdvancedFeatures(_) => CapabilityId::AdvancedFeatures,
+            PciCapability::EnhancedAllocation(_) => CapabilityId::EnhancedAllocation,
+            PciCapability::Unknown { id, .. } => CapabilityId::from(*id),
+        }
+    }
 }
 
 pub struct CapabilityIterator<'a, T: ConfigRegionAccess> {
     address: PciAddress,
     offset: u16,
+    remaining: u8,
     access: &'a T,
 }
 
 impl<'a, T: ConfigRegionAccess> CapabilityIterator<'a, T> {
+    /// Iterate over the capability list of a function with any header type, which is found through the
+    /// Capabilities Pointer at `0x34`, or at `0x14` for CardBus bridges
+    pub(crate) fn for_header(header: &PciHeader, access: &'a T) -> CapabilityIterator<'a, T> {
+        let pointer = if header.status(access).has_capability_list() {
+            let offset = match header.header_type(access) {
+                HeaderType::CardBusBridge => 0x14,
+                _ => 0x34,
+            };
+            unsafe { access.read(header.address(), offset) }.get_bits(0..8) as u16 & 0xfc
+        } else {
+            0
+        };
+        CapabilityIterator::new(header.address(), pointer, access)
+    }
+
     pub(crate) fn new(
         address: PciAddress,
         offset: u16,
@@ -100,33 +454,75 @@ impl<'a, T: ConfigRegionAccess> CapabilityIterator<'a, T> {
         CapabilityIterator {
             address,
             offset,
+            /*
+             * Capabilities are at least a dword long and live between the end of the header and the end of the
+             * configuration space, so there can't be more than this many. This stops us from looping forever on a
+             * malformed list that links back on itself.
+             */
+            remaining: ((0x100 - 0x40) / 4) as u8,
             access,
         }
     }
 }
 
+impl<'a, T: ConfigRegionAccess> CapabilityIterator<'a, T> {
+    /// Move on to the next capability in the list, including Null capabilities, and return its location along
+    /// with its first dword
+    fn next_raw(&mut self) -> Option<(PciCapabilityAddress, u32)> {
+        if self.offset == 0 || self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let address = PciCapabilityAddress {
+            address: self.address,
+            offset: self.offset,
+        };
+        let data = unsafe { self.access.read(self.address, self.offset) };
+        /*
+         * The bottom two bits of the next pointer are reserved, and must be masked off before it is
+         * followed. Some devices do set them.
+         */
+        self.offset = data.get_bits(8..16) as u16 & 0xfc;
+        Some((address, data))
+    }
+
+    /// Like [`CapabilityIterator::next_raw`], but also check that the capability starts after the header and that
+    /// its registers fit in the configuration space. The walk ends after the first capability that doesn't, as its
+    /// next pointer can't be trusted either.
+    pub(crate) fn try_next_raw(
+        &mut self,
+    ) -> Option<Result<(PciCapabilityAddress, u32), CapabilityParseError>> {
+        let (address, data) = self.next_raw()?;
+        let id = CapabilityId::from(data.get_bits(0..8) as u8);
+        let length = validate::standard_length(id, data);
+        if address.offset < 0x40 || address.offset + length > 0x100 {
+            self.offset = 0;
+            return Some(Err(CapabilityParseError::OutOfBounds {
+                offset: address.offset,
+                id,
+                length,
+            }));
+        }
+        Some(Ok((address, data)))
+    }
+}
+
 impl<'a, T: ConfigRegionAccess> Iterator for CapabilityIterator<'a, T> {
     type Item = PciCapability;
 
     fn next(&mut self) -> Option<Self::Item> {
+        /*
+         * Some devices pad their list with Null capabilities. These have no registers, but their next pointer is
+         * still valid, so they are skipped rather than ending the list.
+         */
         loop {
-            if self.offset == 0 {
-                return None;
-            }
-            let data = unsafe { self.access.read(self.address, self.offset) };
-            let next_ptr = data.get_bits(8..16);
-            let id = data.get_bits(0..8);
-            let extension = data.get_bits(16..32) as u16;
-            let cap = PciCapability::parse(
-                id as u8,
-                PciCapabilityAddress {
-                    address: self.address,
-                    offset: self.offset,
-                },
-                extension,
-            );
-            self.offset = next_ptr as u16;
-            if let Some(cap) = cap {
+            let (address, data) = self.next_raw()?;
+            if let Some(cap) = PciCapability::parse(
+                data.get_bits(0..8) as u8,
+                address,
+                data.get_bits(16..32) as u16,
+            ) {
                 return Some(cap);
             }
         }
@@ -65,6 +65,12 @@ impl MsiCapability {
         }
     }
 
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
     /// Does device supports masking individual vectors?
     #[inline]
     pub fn has_per_vector_masking(&self) -> bool {
@@ -133,7 +139,7 @@ impl MsiCapability {
         access: &impl ConfigRegionAccess,
     ) {
         unsafe { access.write(self.address.address, self.address.offset + 0x4, address) }
-        let data_offset = if self.is_64bit { 0xC } else { 0x8 };
+        let data_offset = self.data_offset();
         let mut data =
             unsafe { access.read(self.address.address, self.address.offset + data_offset) };
         data.set_bits(0..8, vector as u32);
@@ -147,14 +153,55 @@ impl MsiCapability {
         }
     }
 
+    /// Read back the message address and data and check that they match `address` and `data`. Some devices
+    /// (especially emulated ones) silently drop writes to these registers, so this can be used to verify that
+    /// [`MsiCapability::set_message_info`] took effect.
+    ///
+    /// `data` is compared against the whole 16-bit Message Data register, including the trigger mode bits.
+    pub fn verify(&self, access: &impl ConfigRegionAccess, address: u32, data: u16) -> bool {
+        let actual_address =
+            unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        let actual_data = unsafe {
+            access.read(
+                self.address.address,
+                self.address.offset + self.data_offset(),
+            )
+        };
+        actual_address == address && actual_data.get_bits(0..16) as u16 == data
+    }
+
+    /// Offset of the Message Data register from the start of the capability, which depends on whether the
+    /// message address is 64 bits wide
+    pub(crate) fn data_offset(&self) -> u16 {
+        if self.is_64bit {
+            0xC
+        } else {
+            0x8
+        }
+    }
+
+    /// Offset of the Mask Bits register from the start of the capability. Both it and the Pending Bits register
+    /// follow the Message Data register, so their offsets also depend on the width of the message address.
+    pub(crate) fn mask_offset(&self) -> u16 {
+        self.data_offset() + 0x4
+    }
+
+    fn pending_offset(&self) -> u16 {
+        self.data_offset() + 0x8
+    }
+
     /// Get interrupt mask
     ///
     /// # Note
-    /// Only supported on when device supports 64-bit addressing and per-vector masking. Otherwise
-    /// returns `0`
+    /// Only supported when device supports per-vector masking. Otherwise returns `0`
     pub fn get_message_mask(&self, access: &impl ConfigRegionAccess) -> u32 {
-        if self.is_64bit && self.per_vector_masking {
-            unsafe { access.read(self.address.address, self.address.offset + 0x10) }
+        if self.per_vector_masking {
+            unsafe {
+                access.read(
+                    self.address.address,
+                    self.address.offset + self.mask_offset(),
+                )
+            }
         } else {
             0
         }
@@ -163,21 +210,31 @@ impl MsiCapability {
     /// Set interrupt mask
     ///
     /// # Note
-    /// Only supported on when device supports 64-bit addressing and per-vector masking. Otherwise
-    /// will do nothing
+    /// Only supported when device supports per-vector masking. Otherwise will do nothing
     pub fn set_message_mask(&self, access: &impl ConfigRegionAccess, mask: u32) {
-        if self.is_64bit && self.per_vector_masking {
-            unsafe { access.write(self.address.address, self.address.offset + 0x10, mask) }
+        if self.per_vector_masking {
+            unsafe {
+                access.write(
+                    self.address.address,
+                    self.address.offset + self.mask_offset(),
+                    mask,
+                )
+            }
         }
     }
 
     /// Get pending interrupts
     ///
     /// # Note
-    /// Only supported on when device supports 64-bit addressing. Otherwise will return `0`
+    /// Only supported when device supports per-vector masking. Otherwise will return `0`
     pub fn get_pending(&self, access: &impl ConfigRegionAccess) -> u32 {
-        if self.is_64bit {
-            unsafe { access.read(self.address.address, self.address.offset + 0x14) }
+        if self.per_vector_masking {
+            unsafe {
+                access.read(
+                    self.address.address,
+                    self.address.offset + self.pending_offset(),
+                )
+            }
         } else {
             0
         }
@@ -0,0 +1,1102 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess, ConfigRegister};
+use bit_field::BitField;
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter},
+};
+
+/// Errors that can occur when operating on the PCI Express capability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcieError {
+    /// The function does not advertise Function Level Reset support in its Device Capabilities register
+    FlrNotSupported,
+    /// The requested ASPM mode is not advertised as supported in the Link Capabilities register
+    AspmNotSupported,
+    /// The requested Max Payload Size is not a power of two between 128 and 4096 bytes, or is larger than the
+    /// function supports
+    PayloadSizeNotSupported,
+    /// The register is only present in version 2 and later of the capability
+    NotInVersion1,
+}
+
+/// The Device Capabilities register of the PCI Express capability, describing the features supported by the
+/// function.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct DeviceCapabilities(u32);
+
+impl DeviceCapabilities {
+    pub fn new(value: u32) -> Self {
+        DeviceCapabilities(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// The maximum payload size the function supports for TLPs, in bytes (`128` to `4096`).
+    pub fn max_payload_size(&self) -> u16 {
+        128 << self.0.get_bits(0..3).min(5)
+    }
+
+    /// The number of most significant bits of the function number that are used for Phantom Functions
+    pub fn phantom_functions(&self) -> u8 {
+        self.0.get_bits(3..5) as u8
+    }
+
+    /// Will be `true` if the function supports 8-bit Tag fields as a requester; otherwise, only 5-bit Tags are
+    /// supported.
+    pub fn extended_tag_supported(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    /// Will be `true` if the function implements the error reporting capabilities introduced by PCIe 1.1
+    pub fn role_based_error_reporting(&self) -> bool {
+        self.0.get_bit(15)
+    }
+
+    /// Will be `true` if the function supports the optional Function Level Reset mechanism
+    pub fn function_level_reset(&self) -> bool {
+        self.0.get_bit(28)
+    }
+}
+
+impl Debug for DeviceCapabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DeviceCapabilities")
+            .field("max_payload_size", &self.max_payload_size())
+            .field("phantom_functions", &self.phantom_functions())
+            .field("extended_tag_supported", &self.extended_tag_supported())
+            .field(
+                "role_based_error_reporting",
+                &self.role_based_error_reporting(),
+            )
+            .field("function_level_reset", &self.function_level_reset())
+            .finish()
+    }
+}
+
+/// The Slot Capabilities register of the PCI Express capability, describing the features of the slot a port is
+/// connected to
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct SlotCapabilities(u32);
+
+impl SlotCapabilities {
+    pub fn new(value: u32) -> Self {
+        SlotCapabilities(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn attention_button_present(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    /// Will be `true` if software can control the power of the slot
+    pub fn power_controller_present(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    /// Will be `true` if the slot has a Manually-operated Retention Latch sensor
+    pub fn mrl_sensor_present(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    pub fn attention_indicator_present(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn power_indicator_present(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    /// Will be `true` if an adapter can be removed from the slot without prior notification
+    pub fn hot_plug_surprise(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn hot_plug_capable(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    /// Will be `true` if the slot does not generate software notifications when a command completes
+    pub fn no_command_completed_support(&self) -> bool {
+        self.0.get_bit(18)
+    }
+
+    /// The physical slot number, which is unique within the chassis
+    pub fn physical_slot_number(&self) -> u16 {
+        self.0.get_bits(19..32) as u16
+    }
+}
+
+impl Debug for SlotCapabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SlotCapabilities")
+            .field("attention_button_present", &self.attention_button_present())
+            .field("power_controller_present", &self.power_controller_present())
+            .field("mrl_sensor_present", &self.mrl_sensor_present())
+            .field(
+                "attention_indicator_present",
+                &self.attention_indicator_present(),
+            )
+            .field("power_indicator_present", &self.power_indicator_present())
+            .field("hot_plug_surprise", &self.hot_plug_surprise())
+            .field("hot_plug_capable", &self.hot_plug_capable())
+            .field(
+                "no_command_completed_support",
+                &self.no_command_completed_support(),
+            )
+            .field("physical_slot_number", &self.physical_slot_number())
+            .finish()
+    }
+}
+
+/// State of one of a slot's indicators (e.g. the power indicator)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IndicatorState {
+    On = 0b01,
+    Blink = 0b10,
+    Off = 0b11,
+}
+
+impl TryFrom<u8> for IndicatorState {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b01 => Ok(IndicatorState::On),
+            0b10 => Ok(IndicatorState::Blink),
+            0b11 => Ok(IndicatorState::Off),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The Slot Control register of the PCI Express capability
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct SlotControl(u16);
+
+impl SlotControl {
+    pub fn new(value: u16) -> Self {
+        SlotControl(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn attention_button_pressed_enable(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn set_attention_button_pressed_enable(&mut self, enabled: bool) {
+        self.0.set_bit(0, enabled);
+    }
+
+    pub fn power_fault_detected_enable(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    pub fn set_power_fault_detected_enable(&mut self, enabled: bool) {
+        self.0.set_bit(1, enabled);
+    }
+
+    pub fn mrl_sensor_changed_enable(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    pub fn set_mrl_sensor_changed_enable(&mut self, enabled: bool) {
+        self.0.set_bit(2, enabled);
+    }
+
+    pub fn presence_detect_changed_enable(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn set_presence_detect_changed_enable(&mut self, enabled: bool) {
+        self.0.set_bit(3, enabled);
+    }
+
+    pub fn command_completed_interrupt_enable(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn set_command_completed_interrupt_enable(&mut self, enabled: bool) {
+        self.0.set_bit(4, enabled);
+    }
+
+    pub fn hot_plug_interrupt_enable(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn set_hot_plug_interrupt_enable(&mut self, enabled: bool) {
+        self.0.set_bit(5, enabled);
+    }
+
+    /// Returns `None` if the indicator control field holds the reserved value `0b00`
+    pub fn attention_indicator(&self) -> Option<IndicatorState> {
+        IndicatorState::try_from(self.0.get_bits(6..8) as u8).ok()
+    }
+
+    pub fn set_attention_indicator(&mut self, state: IndicatorState) {
+        self.0.set_bits(6..8, state as u16);
+    }
+
+    /// Returns `None` if the indicator control field holds the reserved value `0b00`
+    pub fn power_indicator(&self) -> Option<IndicatorState> {
+        IndicatorState::try_from(self.0.get_bits(8..10) as u8).ok()
+    }
+
+    pub fn set_power_indicator(&mut self, state: IndicatorState) {
+        self.0.set_bits(8..10, state as u16);
+    }
+
+    /// Will be `true` if power to the slot is turned off
+    pub fn power_off(&self) -> bool {
+        self.0.get_bit(10)
+    }
+
+    pub fn set_power_off(&mut self, off: bool) {
+        self.0.set_bit(10, off);
+    }
+
+    pub fn data_link_layer_state_changed_enable(&self) -> bool {
+        self.0.get_bit(12)
+    }
+
+    pub fn set_data_link_layer_state_changed_enable(&mut self, enabled: bool) {
+        self.0.set_bit(12, enabled);
+    }
+}
+
+impl ConfigRegister for SlotControl {
+    type Raw = u16;
+
+    fn raw(&self) -> u16 {
+        self.0
+    }
+
+    fn from_raw(raw: u16) -> Self {
+        SlotControl(raw)
+    }
+
+    fn reserved_mask() -> u16 {
+        0x8000
+    }
+}
+
+impl Debug for SlotControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SlotControl")
+            .field(
+                "attention_button_pressed_enable",
+                &self.attention_button_pressed_enable(),
+            )
+            .field(
+                "power_fault_detected_enable",
+                &self.power_fault_detected_enable(),
+            )
+            .field(
+                "mrl_sensor_changed_enable",
+                &self.mrl_sensor_changed_enable(),
+            )
+            .field(
+                "presence_detect_changed_enable",
+                &self.presence_detect_changed_enable(),
+            )
+            .field(
+                "command_completed_interrupt_enable",
+                &self.command_completed_interrupt_enable(),
+            )
+            .field(
+                "hot_plug_interrupt_enable",
+                &self.hot_plug_interrupt_enable(),
+            )
+            .field("attention_indicator", &self.attention_indicator())
+            .field("power_indicator", &self.power_indicator())
+            .field("power_off", &self.power_off())
+            .field(
+                "data_link_layer_state_changed_enable",
+                &self.data_link_layer_state_changed_enable(),
+            )
+            .finish()
+    }
+}
+
+/// The Slot Status register of the PCI Express capability
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct SlotStatus(u16);
+
+impl SlotStatus {
+    /// Bits of the Slot Status register that are cleared by writing `1` to them (RW1C)
+    pub const RW1C_MASK: u16 = 0x011f;
+
+    pub fn new(value: u16) -> Self {
+        SlotStatus(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn attention_button_pressed(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn power_fault_detected(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    pub fn mrl_sensor_changed(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    pub fn presence_detect_changed(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn command_completed(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    /// Will be `true` if the Manually-operated Retention Latch is open
+    pub fn mrl_sensor_open(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    /// Will be `true` if an adapter is present in the slot
+    pub fn presence_detected(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    pub fn electromechanical_interlock_engaged(&self) -> bool {
+        self.0.get_bit(7)
+    }
+
+    pub fn data_link_layer_state_changed(&self) -> bool {
+        self.0.get_bit(8)
+    }
+}
+
+impl ConfigRegister for SlotStatus {
+    type Raw = u16;
+
+    fn raw(&self) -> u16 {
+        self.0
+    }
+
+    fn from_raw(raw: u16) -> Self {
+        SlotStatus(raw)
+    }
+
+    fn reserved_mask() -> u16 {
+        0xfe00
+    }
+}
+
+impl Debug for SlotStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SlotStatus")
+            .field("attention_button_pressed", &self.attention_button_pressed())
+            .field("power_fault_detected", &self.power_fault_detected())
+            .field("mrl_sensor_changed", &self.mrl_sensor_changed())
+            .field("presence_detect_changed", &self.presence_detect_changed())
+            .field("command_completed", &self.command_completed())
+            .field("mrl_sensor_open", &self.mrl_sensor_open())
+            .field("presence_detected", &self.presence_detected())
+            .field(
+                "electromechanical_interlock_engaged",
+                &self.electromechanical_interlock_engaged(),
+            )
+            .field(
+                "data_link_layer_state_changed",
+                &self.data_link_layer_state_changed(),
+            )
+            .finish()
+    }
+}
+
+/// Active State Power Management (ASPM) modes, as used by the ASPM Control field of the Link Control register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AspmControl {
+    Disabled = 0b00,
+    L0s = 0b01,
+    L1 = 0b10,
+    L0sAndL1 = 0b11,
+}
+
+impl TryFrom<u8> for AspmControl {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b00 => Ok(AspmControl::Disabled),
+            0b01 => Ok(AspmControl::L0s),
+            0b10 => Ok(AspmControl::L1),
+            0b11 => Ok(AspmControl::L0sAndL1),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The Link Capabilities register of the PCI Express capability
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct LinkCapabilities(u32);
+
+impl LinkCapabilities {
+    pub fn new(value: u32) -> Self {
+        LinkCapabilities(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// The maximum link speed, encoded as an index into the Supported Link Speeds Vector (`1` for 2.5GT/s, `2`
+    /// for 5GT/s, and so on)
+    pub fn max_link_speed(&self) -> u8 {
+        self.0.get_bits(0..4) as u8
+    }
+
+    /// The maximum number of lanes of the link
+    pub fn max_link_width(&self) -> u8 {
+        self.0.get_bits(4..10) as u8
+    }
+
+    /// Will be `true` if the port supports entering L0s under ASPM
+    pub fn aspm_l0s_supported(&self) -> bool {
+        self.0.get_bit(10)
+    }
+
+    /// Will be `true` if the port supports entering L1 under ASPM
+    pub fn aspm_l1_supported(&self) -> bool {
+        self.0.get_bit(11)
+    }
+
+    /// Will be `true` if the port supports the given ASPM mode. Disabling ASPM is always supported.
+    pub fn supports_aspm(&self, aspm: AspmControl) -> bool {
+        match aspm {
+            AspmControl::Disabled => true,
+            AspmControl::L0s => self.aspm_l0s_supported(),
+            AspmControl::L1 => self.aspm_l1_supported(),
+            AspmControl::L0sAndL1 => self.aspm_l0s_supported() && self.aspm_l1_supported(),
+        }
+    }
+
+    pub fn clock_power_management(&self) -> bool {
+        self.0.get_bit(18)
+    }
+
+    /// The number assigned to the port by the component it belongs to
+    pub fn port_number(&self) -> u8 {
+        self.0.get_bits(24..32) as u8
+    }
+}
+
+impl Debug for LinkCapabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LinkCapabilities")
+            .field("max_link_speed", &self.max_link_speed())
+            .field("max_link_width", &self.max_link_width())
+            .field("aspm_l0s_supported", &self.aspm_l0s_supported())
+            .field("aspm_l1_supported", &self.aspm_l1_supported())
+            .field("clock_power_management", &self.clock_power_management())
+            .field("port_number", &self.port_number())
+            .finish()
+    }
+}
+
+/// The Link Control register of the PCI Express capability
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct LinkControl(u16);
+
+impl LinkControl {
+    pub fn new(value: u16) -> Self {
+        LinkControl(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn aspm_control(&self) -> AspmControl {
+        AspmControl::try_from(self.0.get_bits(0..2) as u8).unwrap()
+    }
+
+    pub fn set_aspm_control(&mut self, aspm: AspmControl) {
+        self.0.set_bits(0..2, aspm as u16);
+    }
+
+    /// Will be `true` if the link is disabled. Only meaningful for downstream ports.
+    pub fn link_disable(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn set_link_disable(&mut self, disabled: bool) {
+        self.0.set_bit(4, disabled);
+    }
+
+    /// Setting this makes a downstream port retrain its link. It always reads as `false`.
+    pub fn set_retrain_link(&mut self, retrain: bool) {
+        self.0.set_bit(5, retrain);
+    }
+
+    /// Will be `true` if the components at both ends of the link use a common reference clock
+    pub fn common_clock_configuration(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    pub fn set_common_clock_configuration(&mut self, common: bool) {
+        self.0.set_bit(6, common);
+    }
+
+    pub fn extended_synch(&self) -> bool {
+        self.0.get_bit(7)
+    }
+
+    pub fn set_extended_synch(&mut self, enabled: bool) {
+        self.0.set_bit(7, enabled);
+    }
+
+    pub fn clock_power_management_enable(&self) -> bool {
+        self.0.get_bit(8)
+    }
+
+    pub fn set_clock_power_management_enable(&mut self, enabled: bool) {
+        self.0.set_bit(8, enabled);
+    }
+}
+
+impl Debug for LinkControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LinkControl")
+            .field("aspm_control", &self.aspm_control())
+            .field("link_disable", &self.link_disable())
+            .field(
+                "common_clock_configuration",
+                &self.common_clock_configuration(),
+            )
+            .field("extended_synch", &self.extended_synch())
+            .field(
+                "clock_power_management_enable",
+                &self.clock_power_management_enable(),
+            )
+            .finish()
+    }
+}
+
+/// The kind of PCI Express function a PCI Express capability belongs to, as given by the Device/Port Type field of
+/// the PCI Express Capabilities register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PciePortType {
+    /// PCI Express Endpoint, Type = `0b0000`
+    EndpointDevice,
+    /// Legacy PCI Express Endpoint, which may use I/O requests and locked transactions, Type = `0b0001`
+    LegacyEndpoint,
+    /// Root Port of a Root Complex, Type = `0b0100`
+    RootPort,
+    /// Upstream Port of a Switch, Type = `0b0101`
+    UpstreamSwitchPort,
+    /// Downstream Port of a Switch, Type = `0b0110`
+    DownstreamSwitchPort,
+    /// PCI Express to PCI/PCI-X bridge, Type = `0b0111`
+    PcieToPciBridge,
+    /// PCI/PCI-X to PCI Express bridge, Type = `0b1000`
+    PciToPcieBridge,
+    /// Endpoint integrated into the Root Complex, Type = `0b1001`
+    RootComplexIntegratedEndpoint,
+    /// Root Complex Event Collector, Type = `0b1010`
+    RootComplexEventCollector,
+    /// Reserved type
+    Reserved(u8),
+}
+
+impl From<u8> for PciePortType {
+    fn from(value: u8) -> Self {
+        match value {
+            0b0000 => PciePortType::EndpointDevice,
+            0b0001 => PciePortType::LegacyEndpoint,
+            0b0100 => PciePortType::RootPort,
+            0b0101 => PciePortType::UpstreamSwitchPort,
+            0b0110 => PciePortType::DownstreamSwitchPort,
+            0b0111 => PciePortType::PcieToPciBridge,
+            0b1000 => PciePortType::PciToPcieBridge,
+            0b1001 => PciePortType::RootComplexIntegratedEndpoint,
+            0b1010 => PciePortType::RootComplexEventCollector,
+            other => PciePortType::Reserved(other),
+        }
+    }
+}
+
+impl PciePortType {
+    /// Will be `true` for ports on the upstream end of a link, below which further functions can be found (Root
+    /// Ports and Downstream Switch Ports)
+    pub fn is_downstream_port(&self) -> bool {
+        matches!(
+            self,
+            PciePortType::RootPort | PciePortType::DownstreamSwitchPort
+        )
+    }
+}
+
+/// Optimized Buffer Flush/Fill (OBFF) signalling modes, as used by the OBFF Enable field of the Device Control 2
+/// register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObffEnable {
+    Disabled = 0b00,
+    /// Signal OBFF using messages, forwarding any received WAKE# signals as messages too
+    MessageVariationA = 0b01,
+    /// Signal OBFF using messages, without forwarding WAKE# signals
+    MessageVariationB = 0b10,
+    /// Signal OBFF using the WAKE# signal
+    WakeSignaling = 0b11,
+}
+
+impl TryFrom<u8> for ObffEnable {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0b00 => Ok(ObffEnable::Disabled),
+            0b01 => Ok(ObffEnable::MessageVariationA),
+            0b10 => Ok(ObffEnable::MessageVariationB),
+            0b11 => Ok(ObffEnable::WakeSignaling),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The Device Control 2 register of the PCI Express capability, only present in version 2 of the capability
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct DeviceControl2(u16);
+
+impl DeviceControl2 {
+    pub fn new(value: u16) -> Self {
+        DeviceControl2(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Will be `true` if the function's completion timeout mechanism is disabled
+    pub fn completion_timeout_disable(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn set_completion_timeout_disable(&mut self, disabled: bool) {
+        self.0.set_bit(4, disabled);
+    }
+
+    /// Will be `true` if a downstream port treats the device number of requests it forwards as part of an ARI
+    /// function number
+    pub fn ari_forwarding_enable(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn set_ari_forwarding_enable(&mut self, enabled: bool) {
+        self.0.set_bit(5, enabled);
+    }
+
+    /// Will be `true` if the function may initiate AtomicOp requests
+    pub fn atomic_op_requester_enable(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    pub fn set_atomic_op_requester_enable(&mut self, enabled: bool) {
+        self.0.set_bit(6, enabled);
+    }
+
+    /// Will be `true` if the function may set the ID-Based Ordering attribute on the requests it initiates
+    pub fn ido_request_enable(&self) -> bool {
+        self.0.get_bit(8)
+    }
+
+    pub fn set_ido_request_enable(&mut self, enabled: bool) {
+        self.0.set_bit(8, enabled);
+    }
+
+    /// Will be `true` if the function may set the ID-Based Ordering attribute on the completions it returns
+    pub fn ido_completion_enable(&self) -> bool {
+        self.0.get_bit(9)
+    }
+
+    pub fn set_ido_completion_enable(&mut self, enabled: bool) {
+        self.0.set_bit(9, enabled);
+    }
+
+    /// Will be `true` if the function may send Latency Tolerance Reporting messages
+    pub fn ltr_enable(&self) -> bool {
+        self.0.get_bit(10)
+    }
+
+    pub fn set_ltr_enable(&mut self, enabled: bool) {
+        self.0.set_bit(10, enabled);
+    }
+
+    /// Will be `true` if the function may use 10-bit tags for the requests it initiates, allowing far more of
+    /// them to be outstanding at once
+    pub fn ten_bit_tag_requester_enable(&self) -> bool {
+        self.0.get_bit(12)
+    }
+
+    pub fn set_ten_bit_tag_requester_enable(&mut self, enabled: bool) {
+        self.0.set_bit(12, enabled);
+    }
+
+    pub fn obff_enable(&self) -> ObffEnable {
+        ObffEnable::try_from(self.0.get_bits(13..15) as u8).unwrap()
+    }
+
+    pub fn set_obff_enable(&mut self, obff: ObffEnable) {
+        self.0.set_bits(13..15, obff as u16);
+    }
+}
+
+impl Debug for DeviceControl2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DeviceControl2")
+            .field(
+                "completion_timeout_disable",
+                &self.completion_timeout_disable(),
+            )
+            .field("ari_forwarding_enable", &self.ari_forwarding_enable())
+            .field(
+                "atomic_op_requester_enable",
+                &self.atomic_op_requester_enable(),
+            )
+            .field("ido_request_enable", &self.ido_request_enable())
+            .field("ido_completion_enable", &self.ido_completion_enable())
+            .field("ltr_enable", &self.ltr_enable())
+            .field(
+                "ten_bit_tag_requester_enable",
+                &self.ten_bit_tag_requester_enable(),
+            )
+            .field("obff_enable", &self.obff_enable())
+            .finish()
+    }
+}
+
+/// The PCI Express capability, present on all PCI Express functions
+#[derive(Debug, Clone)]
+pub struct PcieCapability {
+    address: PciCapabilityAddress,
+    capabilities: u16,
+}
+
+impl PcieCapability {
+    pub(crate) fn new(address: PciCapabilityAddress, capabilities: u16) -> PcieCapability {
+        PcieCapability {
+            address,
+            capabilities,
+        }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// Raw contents of the PCI Express Capabilities register
+    #[inline]
+    pub fn capabilities_register(&self) -> u16 {
+        self.capabilities
+    }
+
+    /// The version of the capability. Version 1 capabilities end after the Slot registers, and only version 2
+    /// capabilities have the Device, Link and Slot Capabilities 2, Control 2 and Status 2 registers.
+    pub fn version(&self) -> u8 {
+        self.capabilities.get_bits(0..4) as u8
+    }
+
+    /// The kind of function this capability belongs to. Which of the Link, Slot and Root registers are
+    /// meaningful depends on it.
+    pub fn port_type(&self) -> PciePortType {
+        PciePortType::from(self.capabilities.get_bits(4..8) as u8)
+    }
+
+    /// Will be `true` if the function is an endpoint integrated into the Root Complex, rather than one on the far
+    /// side of a link (e.g. an add-in card)
+    pub fn is_integrated_endpoint(&self) -> bool {
+        self.port_type() == PciePortType::RootComplexIntegratedEndpoint
+    }
+
+    /// Will be `true` if the port is connected to a slot (rather than an integrated component or a disabled
+    /// port). The slot registers are only available if this is set. Only downstream ports can be connected to a
+    /// slot, so this is always `false` for other kinds of function.
+    pub fn slot_implemented(&self) -> bool {
+        self.port_type().is_downstream_port() && self.capabilities.get_bit(8)
+    }
+
+    /// Read the Device Capabilities register
+    pub fn device_capabilities(&self, access: &impl ConfigRegionAccess) -> DeviceCapabilities {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        DeviceCapabilities::new(reg)
+    }
+
+    /// Does the function support Function Level Reset?
+    pub fn supports_flr(&self, access: &impl ConfigRegionAccess) -> bool {
+        self.device_capabilities(access).function_level_reset()
+    }
+
+    /// Will be `true` if the Transactions Pending bit of the Device Status register is set, i.e. the function has
+    /// issued requests that haven't completed yet
+    pub fn transactions_pending(&self, access: &impl ConfigRegionAccess) -> bool {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        reg.get_bit(21)
+    }
+
+    /// Initiate a Function Level Reset of this function. Returns [`PcieError::FlrNotSupported`] without
+    /// touching the device if the function doesn't advertise FLR support.
+    ///
+    /// # Note
+    /// The function is not guaranteed to be usable until 100ms after the reset has been initiated. The caller
+    /// must wait at least this long before accessing the function's configuration space again. The reset also
+    /// clears most of the function's configuration, so it must be reprogrammed afterwards.
+    pub fn initiate_flr(&self, access: &impl ConfigRegionAccess) -> Result<(), PcieError> {
+        if !self.supports_flr(access) {
+            return Err(PcieError::FlrNotSupported);
+        }
+
+        /*
+         * The upper half of the dword is the Device Status register, whose error bits are RW1C. Zero them so
+         * that writing back the Device Control register doesn't clear them as a side-effect.
+         */
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        reg.set_bits(16..32, 0);
+        reg.set_bit(15, true);
+        unsafe { access.write(self.address.address, self.address.offset + 0x8, reg) };
+        Ok(())
+    }
+
+    /// Enable or disable the reporting of correctable, non-fatal, fatal and Unsupported Request errors in the
+    /// Device Control register
+    pub fn set_error_reporting(&self, access: &impl ConfigRegionAccess, enabled: bool) {
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        reg.set_bits(16..32, 0);
+        reg.set_bits(0..4, if enabled { 0xf } else { 0 });
+        unsafe { access.write(self.address.address, self.address.offset + 0x8, reg) };
+    }
+
+    /// Clear the error bits of the Device Status register (correctable, non-fatal, fatal and Unsupported Request
+    /// errors detected), leaving the Device Control register unchanged
+    pub fn clear_device_errors(&self, access: &impl ConfigRegionAccess) {
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        reg.set_bits(16..32, 0xf);
+        unsafe { access.write(self.address.address, self.address.offset + 0x8, reg) };
+    }
+
+    /// The Max Payload Size the function is currently programmed with, in bytes (`128` to `4096`)
+    pub fn max_payload_size(&self, access: &impl ConfigRegionAccess) -> u16 {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        128 << reg.get_bits(5..8).min(5)
+    }
+
+    /// Program the Max Payload Size of the function, in bytes. Returns [`PcieError::PayloadSizeNotSupported`]
+    /// without touching the device if the size can't be encoded or is larger than the function supports.
+    ///
+    /// # Note
+    /// The function must not send or receive TLPs larger than its link partner can handle, so this usually has to
+    /// be done through [`PcieCapability::configure_max_payload_size`] instead.
+    pub fn set_max_payload_size(
+        &self,
+        access: &impl ConfigRegionAccess,
+        size: u16,
+    ) -> Result<(), PcieError> {
+        if !size.is_power_of_two()
+            || !(128..=4096).contains(&size)
+            || size > self.device_capabilities(access).max_payload_size()
+        {
+            return Err(PcieError::PayloadSizeNotSupported);
+        }
+
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        reg.set_bits(16..32, 0);
+        reg.set_bits(5..8, (size / 128).trailing_zeros());
+        unsafe { access.write(self.address.address, self.address.offset + 0x8, reg) };
+        Ok(())
+    }
+
+    /// The largest Max Payload Size that both ends of a link support, in bytes. `upstream` is the PCI Express
+    /// capability of the port above the function this capability belongs to.
+    pub fn common_max_payload_size(
+        &self,
+        upstream: &PcieCapability,
+        access: &impl ConfigRegionAccess,
+    ) -> u16 {
+        let supported = self.device_capabilities(access).max_payload_size();
+        supported.min(upstream.device_capabilities(access).max_payload_size())
+    }
+
+    /// Program both this function and the port above it (`upstream`) with the largest Max Payload Size they both
+    /// support, and return it.
+    ///
+    /// ### Note
+    /// This only covers a single link. TLPs travel through every port between the function and the root port, so
+    /// the caller should find the smallest size supported along that whole path and program every port with it.
+    pub fn configure_max_payload_size(
+        &self,
+        upstream: &PcieCapability,
+        access: &impl ConfigRegionAccess,
+    ) -> u16 {
+        let size = self.common_max_payload_size(upstream, access);
+        /*
+         * The size is supported by both functions, so neither write can fail.
+         */
+        let _ = upstream.set_max_payload_size(access, size);
+        let _ = self.set_max_payload_size(access, size);
+        size
+    }
+
+    /// Read the Device Control 2 register. Returns `None` if this is version 1 of the capability, which doesn't
+    /// have it.
+    pub fn device_control_2(&self, access: &impl ConfigRegionAccess) -> Option<DeviceControl2> {
+        if self.version() < 2 {
+            return None;
+        }
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x28) };
+        Some(DeviceControl2::new(reg.get_bits(0..16) as u16))
+    }
+
+    /// Write the Device Control 2 register. Returns [`PcieError::NotInVersion1`] without touching the device if
+    /// this is version 1 of the capability, which doesn't have the register.
+    ///
+    /// # Note
+    /// Features like 10-bit tags and OBFF must be supported by the function (and for some, by every port on the
+    /// path to the root complex) before they are enabled. This is advertised by the Device Capabilities 2
+    /// register, which the caller is expected to check.
+    pub fn set_device_control_2(
+        &self,
+        access: &impl ConfigRegionAccess,
+        control: DeviceControl2,
+    ) -> Result<(), PcieError> {
+        if self.version() < 2 {
+            return Err(PcieError::NotInVersion1);
+        }
+        /*
+         * The upper half of the dword is the Device Status 2 register, which has no defined bits.
+         */
+        unsafe {
+            access.write(
+                self.address.address,
+                self.address.offset + 0x28,
+                control.bits() as u32,
+            )
+        };
+        Ok(())
+    }
+
+    /// Read the Link Capabilities register
+    pub fn link_capabilities(&self, access: &impl ConfigRegionAccess) -> LinkCapabilities {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0xc) };
+        LinkCapabilities::new(reg)
+    }
+
+    /// Read the Link Control register
+    pub fn link_control(&self, access: &impl ConfigRegionAccess) -> LinkControl {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x10) };
+        LinkControl::new(reg.get_bits(0..16) as u16)
+    }
+
+    /// Write the Link Control register
+    pub fn set_link_control(&self, access: &impl ConfigRegionAccess, control: LinkControl) {
+        /*
+         * The upper half of the dword is the Link Status register, which has RW1C bits that writing zeroes leaves
+         * alone.
+         */
+        unsafe {
+            access.write(
+                self.address.address,
+                self.address.offset + 0x10,
+                control.bits() as u32,
+            )
+        };
+    }
+
+    /// Set which link states the port may enter under Active State Power Management. Returns
+    /// [`PcieError::AspmNotSupported`] without touching the device if the Link Capabilities register doesn't
+    /// advertise support for the mode, as enabling an unsupported mode can hang the link.
+    ///
+    /// # Note
+    /// ASPM should be configured consistently at both ends of a link, so the caller is expected to do the same
+    /// for the port at the other end.
+    pub fn set_aspm(
+        &self,
+        access: &impl ConfigRegionAccess,
+        aspm: AspmControl,
+    ) -> Result<(), PcieError> {
+        if !self.link_capabilities(access).supports_aspm(aspm) {
+            return Err(PcieError::AspmNotSupported);
+        }
+
+        let mut control = self.link_control(access);
+        control.set_aspm_control(aspm);
+        self.set_link_control(access, control);
+        Ok(())
+    }
+
+    /// Read the Slot Capabilities register. Returns `None` if the port isn't connected to a slot.
+    pub fn slot_capabilities(&self, access: &impl ConfigRegionAccess) -> Option<SlotCapabilities> {
+        if !self.slot_implemented() {
+            return None;
+        }
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x14) };
+        Some(SlotCapabilities::new(reg))
+    }
+
+    /// Read the Slot Control register. Returns `None` if the port isn't connected to a slot.
+    pub fn slot_control(&self, access: &impl ConfigRegionAccess) -> Option<SlotControl> {
+        if !self.slot_implemented() {
+            return None;
+        }
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x18) };
+        Some(SlotControl::new(reg.get_bits(0..16) as u16))
+    }
+
+    /// Write the Slot Control register. Does nothing if the port isn't connected to a slot.
+    pub fn set_slot_control(&self, access: &impl ConfigRegionAccess, control: SlotControl) {
+        if self.slot_implemented() {
+            /*
+             * The upper half of the dword is the Slot Status register. Writing zeroes to it leaves its RW1C bits
+             * untouched.
+             */
+            unsafe {
+                access.write(
+                    self.address.address,
+                    self.address.offset + 0x18,
+                    control.bits() as u32,
+                )
+            };
+        }
+    }
+
+    /// Read the Slot Status register. Returns `None` if the port isn't connected to a slot.
+    pub fn slot_status(&self, access: &impl ConfigRegionAccess) -> Option<SlotStatus> {
+        if !self.slot_implemented() {
+            return None;
+        }
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x18) };
+        Some(SlotStatus::new(reg.get_bits(16..32) as u16))
+    }
+
+    /// Clear the RW1C bits of the Slot Status register that are set in `bits` (e.g. to acknowledge a presence
+    /// detect change), leaving the Slot Control register unchanged. Does nothing if the port isn't connected to a
+    /// slot.
+    pub fn clear_slot_status(&self, access: &impl ConfigRegionAccess, bits: u16) {
+        if self.slot_implemented() {
+            let mut reg = unsafe { access.read(self.address.address, self.address.offset + 0x18) };
+            reg.set_bits(16..32, (bits & SlotStatus::RW1C_MASK) as u32);
+            unsafe { access.write(self.address.address, self.address.offset + 0x18, reg) };
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess};
+use bit_field::BitField;
+use core::fmt::{Debug, Formatter};
+
+/// The PCI-X Command register of a PCI-X function
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct PciXCommand(u16);
+
+impl PciXCommand {
+    pub fn new(value: u16) -> Self {
+        PciXCommand(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Will be `true` if the function attempts to recover from data parity errors instead of asserting SERR#
+    pub fn data_parity_error_recovery(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn relaxed_ordering(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    /// The maximum byte count the function uses when initiating a memory read sequence (`512` to `4096`)
+    pub fn max_memory_read_byte_count(&self) -> u16 {
+        512 << self.0.get_bits(2..4)
+    }
+
+    /// The maximum number of split transactions the function is allowed to have outstanding at once
+    pub fn max_outstanding_split_transactions(&self) -> u8 {
+        split_transactions(self.0.get_bits(4..7) as u8)
+    }
+
+    /// The version of the PCI-X capability (`0` for PCI-X 1.0, `1` or `2` for PCI-X 2.0 functions)
+    pub fn version(&self) -> u8 {
+        self.0.get_bits(12..14) as u8
+    }
+}
+
+impl Debug for PciXCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PciXCommand")
+            .field(
+                "data_parity_error_recovery",
+                &self.data_parity_error_recovery(),
+            )
+            .field("relaxed_ordering", &self.relaxed_ordering())
+            .field(
+                "max_memory_read_byte_count",
+                &self.max_memory_read_byte_count(),
+            )
+            .field(
+                "max_outstanding_split_transactions",
+                &self.max_outstanding_split_transactions(),
+            )
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+/// The PCI-X Status register of a PCI-X function
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct PciXStatus(u32);
+
+impl PciXStatus {
+    pub fn new(value: u32) -> Self {
+        PciXStatus(value)
+    }
+
+    /// Raw value of the register
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// The function number the function was last addressed with
+    pub fn function_number(&self) -> u8 {
+        self.0.get_bits(0..3) as u8
+    }
+
+    /// The device number the function was last addressed with
+    pub fn device_number(&self) -> u8 {
+        self.0.get_bits(3..8) as u8
+    }
+
+    /// The number of the bus the function is on, as captured from the last configuration write to it
+    pub fn bus_number(&self) -> u8 {
+        self.0.get_bits(8..16) as u8
+    }
+
+    /// Will be `true` if the function has a 64-bit AD bus; otherwise, it is 32 bits wide.
+    pub fn device_64bit(&self) -> bool {
+        self.0.get_bit(16)
+    }
+
+    /// Will be `true` if the function can run at 133MHz; otherwise, its maximum frequency is 66MHz.
+    pub fn capable_133mhz(&self) -> bool {
+        self.0.get_bit(17)
+    }
+
+    /// Will be `true` if the function discarded a split completion because the requester would not accept it
+    pub fn split_completion_discarded(&self) -> bool {
+        self.0.get_bit(18)
+    }
+
+    /// Will be `true` if the function received a split completion it has no outstanding request for
+    pub fn unexpected_split_completion(&self) -> bool {
+        self.0.get_bit(19)
+    }
+
+    /// Will be `true` if the function is a bridge device
+    pub fn bridge(&self) -> bool {
+        self.0.get_bit(20)
+    }
+
+    /// The largest memory read byte count the function is designed to use, in bytes (`512` to `4096`)
+    pub fn designed_max_memory_read_byte_count(&self) -> u16 {
+        512 << self.0.get_bits(21..23)
+    }
+
+    /// The number of outstanding split transactions the function is designed to use
+    pub fn designed_max_outstanding_split_transactions(&self) -> u8 {
+        split_transactions(self.0.get_bits(23..26) as u8)
+    }
+
+    /// The maximum number of outstanding memory reads the function is designed to have, in multiples of 1KiB
+    /// for PCI-X 1.0 functions (`8` to `1024`)
+    pub fn designed_max_cumulative_read_size(&self) -> u16 {
+        8 << self.0.get_bits(26..29)
+    }
+
+    /// Will be `true` if the function received a split completion message indicating an error
+    pub fn received_split_completion_error(&self) -> bool {
+        self.0.get_bit(29)
+    }
+
+    /// Will be `true` if the function supports PCI-X 266 (Mode 2)
+    pub fn capable_266mhz(&self) -> bool {
+        self.0.get_bit(30)
+    }
+
+    /// Will be `true` if the function supports PCI-X 533 (Mode 2)
+    pub fn capable_533mhz(&self) -> bool {
+        self.0.get_bit(31)
+    }
+
+    /// The width of the function's AD bus, in bits (`32` or `64`)
+    pub fn bus_width(&self) -> u8 {
+        if self.device_64bit() {
+            64
+        } else {
+            32
+        }
+    }
+
+    /// The highest transfer rate the function supports, in MHz (`66`, `133`, `266` or `533`)
+    pub fn max_frequency(&self) -> u16 {
+        if self.capable_533mhz() {
+            533
+        } else if self.capable_266mhz() {
+            266
+        } else if self.capable_133mhz() {
+            133
+        } else {
+            66
+        }
+    }
+}
+
+impl Debug for PciXStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PciXStatus")
+            .field("function_number", &self.function_number())
+            .field("device_number", &self.device_number())
+            .field("bus_number", &self.bus_number())
+            .field("bus_width", &self.bus_width())
+            .field("max_frequency", &self.max_frequency())
+            .field(
+                "split_completion_discarded",
+                &self.split_completion_discarded(),
+            )
+            .field(
+                "unexpected_split_completion",
+                &self.unexpected_split_completion(),
+            )
+            .field("bridge", &self.bridge())
+            .field(
+                "designed_max_memory_read_byte_count",
+                &self.designed_max_memory_read_byte_count(),
+            )
+            .field(
+                "designed_max_outstanding_split_transactions",
+                &self.designed_max_outstanding_split_transactions(),
+            )
+            .field(
+                "designed_max_cumulative_read_size",
+                &self.designed_max_cumulative_read_size(),
+            )
+            .field(
+                "received_split_completion_error",
+                &self.received_split_completion_error(),
+            )
+            .finish()
+    }
+}
+
+/// PCI-X capability, found on PCI-X devices (mostly found on older server hardware).
+///
+/// ### Note
+/// PCI-X bridges use a different layout for this capability, with Secondary Status and Bridge Status registers
+/// in place of the Command and Status registers, which is not decoded here.
+#[derive(Debug, Clone)]
+pub struct PciXCapability {
+    address: PciCapabilityAddress,
+}
+
+impl PciXCapability {
+    pub(crate) fn new(address: PciCapabilityAddress) -> PciXCapability {
+        PciXCapability { address }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// Read the PCI-X Command register
+    pub fn command(&self, access: &impl ConfigRegionAccess) -> PciXCommand {
+        let reg = unsafe { access.read(self.address.address, self.address.offset) };
+        PciXCommand::new(reg.get_bits(16..32) as u16)
+    }
+
+    /// Write the PCI-X Command register
+    pub fn set_command(&self, access: &impl ConfigRegionAccess, command: PciXCommand) {
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset) };
+        reg.set_bits(16..32, command.bits() as u32);
+        unsafe { access.write(self.address.address, self.address.offset, reg) }
+    }
+
+    /// Read the PCI-X Status register
+    pub fn status(&self, access: &impl ConfigRegionAccess) -> PciXStatus {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        PciXStatus::new(reg)
+    }
+}
+
+/// Decode the encoding of a number of outstanding split transactions used by the Command and Status registers
+fn split_transactions(encoded: u8) -> u8 {
+    match encoded {
+        0..=3 => encoded + 1,
+        4 => 8,
+        5 => 12,
+        6 => 16,
+        _ => 32,
+    }
+}
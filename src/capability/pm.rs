@@ -0,0 +1,123 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess};
+use bit_field::BitField;
+
+/// A power state of a function, as set through the PowerState field of the PMCSR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    D0 = 0b00,
+    D1 = 0b01,
+    D2 = 0b10,
+    D3Hot = 0b11,
+}
+
+impl From<u8> for PowerState {
+    fn from(value: u8) -> Self {
+        match value & 0b11 {
+            0b00 => PowerState::D0,
+            0b01 => PowerState::D1,
+            0b10 => PowerState::D2,
+            _ => PowerState::D3Hot,
+        }
+    }
+}
+
+/// Power Management capability, which lets software move a function between power states and configure it to
+/// raise a Power Management Event (PME) to wake the system
+#[derive(Debug, Clone)]
+pub struct PowerManagementCapability {
+    address: PciCapabilityAddress,
+    capabilities: u16,
+}
+
+impl PowerManagementCapability {
+    pub(crate) fn new(address: PciCapabilityAddress, extension: u16) -> PowerManagementCapability {
+        PowerManagementCapability {
+            address,
+            capabilities: extension,
+        }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// The version of the PCI Power Management specification the function implements
+    pub fn version(&self) -> u8 {
+        self.capabilities.get_bits(0..3) as u8
+    }
+
+    /// Will be `true` if the function supports the D1 power state
+    pub fn d1_supported(&self) -> bool {
+        self.capabilities.get_bit(9)
+    }
+
+    /// Will be `true` if the function supports the D2 power state
+    pub fn d2_supported(&self) -> bool {
+        self.capabilities.get_bit(10)
+    }
+
+    /// The power states the function can raise a PME from, as a bitmap with bit `0` for D0 up to bit `3` for
+    /// D3hot and bit `4` for D3cold
+    pub fn pme_support(&self) -> u8 {
+        self.capabilities.get_bits(11..16) as u8
+    }
+
+    /// The current power state of the function
+    pub fn power_state(&self, access: &impl ConfigRegionAccess) -> PowerState {
+        PowerState::from(self.pmcsr(access).get_bits(0..2) as u8)
+    }
+
+    /// Move the function to the given power state. A pending PME is left pending.
+    ///
+    /// ### Note
+    /// The function may need up to 10ms to settle after a transition to or from D3hot before it is accessed again.
+    /// Unless [`PowerManagementCapability::no_soft_reset`] is set, moving from D3hot to D0 resets the function,
+    /// so it has to be reconfigured afterwards.
+    pub fn set_power_state(&self, access: &impl ConfigRegionAccess, state: PowerState) {
+        let mut pmcsr = self.pmcsr(access);
+        pmcsr.set_bits(0..2, state as u32);
+        self.write_pmcsr(access, pmcsr, false);
+    }
+
+    /// Will be `true` if the function keeps its configuration when moving from D3hot to D0
+    pub fn no_soft_reset(&self, access: &impl ConfigRegionAccess) -> bool {
+        self.pmcsr(access).get_bit(3)
+    }
+
+    /// Will be `true` if the function is allowed to raise a PME
+    pub fn pme_enabled(&self, access: &impl ConfigRegionAccess) -> bool {
+        self.pmcsr(access).get_bit(8)
+    }
+
+    /// Allow or forbid the function to raise a PME. A pending PME is left pending.
+    pub fn set_pme_enabled(&self, access: &impl ConfigRegionAccess, enabled: bool) {
+        let mut pmcsr = self.pmcsr(access);
+        pmcsr.set_bit(8, enabled);
+        self.write_pmcsr(access, pmcsr, false);
+    }
+
+    /// Will be `true` if the function has raised a PME that hasn't been cleared yet
+    pub fn pme_status(&self, access: &impl ConfigRegionAccess) -> bool {
+        self.pmcsr(access).get_bit(15)
+    }
+
+    /// Clear a pending PME, leaving the rest of the PMCSR as it is
+    pub fn clear_pme_status(&self, access: &impl ConfigRegionAccess) {
+        let pmcsr = self.pmcsr(access);
+        self.write_pmcsr(access, pmcsr, true);
+    }
+
+    fn pmcsr(&self, access: &impl ConfigRegionAccess) -> u32 {
+        unsafe { access.read(self.address.address, self.address.offset + 0x4) }
+    }
+
+    /// Write back a PMCSR value that was read with [`PowerManagementCapability::pmcsr`]. PME_Status is cleared by
+    /// writing `1` to it, so a value read while a PME is pending would clear it: the bit is only written as `1` if
+    /// `clear_pme` is set. The other bits are either read-write, or read-only and so unaffected by the write.
+    fn write_pmcsr(&self, access: &impl ConfigRegionAccess, mut pmcsr: u32, clear_pme: bool) {
+        pmcsr.set_bit(15, clear_pme);
+        unsafe { access.write(self.address.address, self.address.offset + 0x4, pmcsr) }
+    }
+}
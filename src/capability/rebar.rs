@@ -0,0 +1,144 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    BarIndex, ConfigRegionAccess,
+};
+use bit_field::BitField;
+
+/// Errors that can occur when resizing a BAR through the Resizable BAR capability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizableBarError {
+    /// The capability has no entry for the given BAR
+    BarNotResizable,
+    /// The BAR doesn't support the requested size. Sizes must be a power of two of at least 1MiB.
+    SizeNotSupported,
+}
+
+/// The resizing options of a single BAR, as described by an entry of the Resizable BAR capability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResizableBarEntry {
+    pub bar: BarIndex,
+    /// Bit `n` is set if the BAR can be resized to `1MiB << n` bytes
+    pub supported_sizes: u64,
+    /// The size the BAR is currently set to, in bytes
+    pub current_size: u64,
+}
+
+impl ResizableBarEntry {
+    /// Will be `true` if the BAR can be resized to `size` bytes
+    pub fn supports(&self, size: u64) -> bool {
+        size_encoding(size).is_some_and(|encoded| self.supported_sizes.get_bit(encoded as usize))
+    }
+
+    /// Iterate over the sizes the BAR can be resized to, in bytes, from smallest to largest
+    pub fn sizes(&self) -> impl Iterator<Item = u64> {
+        let supported = self.supported_sizes;
+        (0..44)
+            .filter(move |&n| supported.get_bit(n))
+            .map(|n| MIB << n)
+    }
+}
+
+const MIB: u64 = 1 << 20;
+
+/// Resizable BAR extended capability, which lets software change the size of some of a function's BARs (e.g. to
+/// map the whole of a GPU's memory instead of a small aperture)
+#[derive(Debug, Clone)]
+pub struct ResizableBarCapability {
+    address: PciCapabilityAddress,
+}
+
+impl ResizableBarCapability {
+    /// Interpret an extended capability as a Resizable BAR capability. Returns `None` if it is a different
+    /// capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<ResizableBarCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::ResizableBar => Some(ResizableBarCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The number of BARs that can be resized, which is also the number of entries in the capability (`1` to `6`)
+    pub fn num_bars(&self, access: &impl ConfigRegionAccess) -> u8 {
+        /*
+         * Only the Control register of the first entry holds the number of entries.
+         */
+        let control = unsafe { access.read(self.address.address, self.address.offset + 0x8) };
+        control.get_bits(5..8) as u8
+    }
+
+    /// Iterate over the entries of the capability
+    pub fn entries<'a>(
+        &'a self,
+        access: &'a impl ConfigRegionAccess,
+    ) -> impl Iterator<Item = ResizableBarEntry> + 'a {
+        (0..self.num_bars(access).min(6)).filter_map(move |i| self.read_entry(access, i))
+    }
+
+    /// Get the entry describing the given BAR. Returns `None` if the BAR can't be resized.
+    pub fn entry(
+        &self,
+        access: &impl ConfigRegionAccess,
+        bar: BarIndex,
+    ) -> Option<ResizableBarEntry> {
+        self.entries(access).find(|entry| entry.bar == bar)
+    }
+
+    /// Resize the given BAR to `size` bytes.
+    ///
+    /// # Note
+    /// Memory decoding must be disabled in the Command register while a BAR is resized, and the BAR must be
+    /// reprogrammed afterwards, as its address is no longer valid.
+    pub fn set_size(
+        &self,
+        access: &impl ConfigRegionAccess,
+        bar: BarIndex,
+        size: u64,
+    ) -> Result<(), ResizableBarError> {
+        let (index, entry) = (0..self.num_bars(access).min(6))
+            .filter_map(|i| Some((i, self.read_entry(access, i)?)))
+            .find(|(_, entry)| entry.bar == bar)
+            .ok_or(ResizableBarError::BarNotResizable)?;
+        if !entry.supports(size) {
+            return Err(ResizableBarError::SizeNotSupported);
+        }
+
+        let offset = self.address.offset + 0x8 + index as u16 * 8;
+        let mut control = unsafe { access.read(self.address.address, offset) };
+        control.set_bits(8..14, size_encoding(size).unwrap() as u32);
+        unsafe { access.write(self.address.address, offset, control) };
+        Ok(())
+    }
+
+    fn read_entry(&self, access: &impl ConfigRegionAccess, index: u8) -> Option<ResizableBarEntry> {
+        let offset = self.address.offset + 0x4 + index as u16 * 8;
+        let capability = unsafe { access.read(self.address.address, offset) };
+        let control = unsafe { access.read(self.address.address, offset + 0x4) };
+
+        /*
+         * The Capability register covers sizes from 1MiB to 128TiB, and the upper half of the Control register
+         * extends this with larger sizes.
+         */
+        let mut supported_sizes = capability.get_bits(4..32) as u64;
+        supported_sizes.set_bits(28..44, control.get_bits(16..32) as u64);
+
+        Some(ResizableBarEntry {
+            bar: BarIndex::new(control.get_bits(0..3) as u8)?,
+            supported_sizes,
+            /*
+             * Sizes above 8EiB don't fit in a `u64`, and are not defined by the specification anyway.
+             */
+            current_size: MIB << control.get_bits(8..14).min(43),
+        })
+    }
+}
+
+/// Encode a size in bytes as used by the BAR Size field, i.e. `n` for a size of `1MiB << n`. Returns `None` if
+/// the size can't be encoded.
+fn size_encoding(size: u64) -> Option<u8> {
+    if size < MIB || !size.is_power_of_two() {
+        return None;
+    }
+    Some((size / MIB).trailing_zeros() as u8)
+}
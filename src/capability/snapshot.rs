@@ -0,0 +1,125 @@
+use crate::{
+    capability::{
+        CapabilityId, CapabilityIterator, ExtendedCapabilityId, ExtendedCapabilityIterator,
+    },
+    ConfigRegionAccess, PciHeader,
+};
+use alloc::vec::Vec;
+use bit_field::BitField;
+
+/// The kind of a capability in a [`CapabilitySnapshot`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CapabilityKind {
+    Standard(CapabilityId),
+    Extended {
+        id: ExtendedCapabilityId,
+        version: u8,
+    },
+}
+
+/// A single capability, as captured by [`CapabilitySnapshot::capture`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CapabilityEntry {
+    pub kind: CapabilityKind,
+    /// Location of the capability in configuration space
+    pub offset: u16,
+    /// The contents of the capability, from its header to the end of its registers. The next pointer is cleared
+    /// from the header, so that capabilities don't compare as changed just because the list was reordered.
+    pub dwords: Vec<u32>,
+}
+
+/// A difference between two [`CapabilitySnapshot`]s, as found by [`CapabilitySnapshot::diff`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CapabilityChange<'a> {
+    /// The capability is only in the newer snapshot
+    Appeared(&'a CapabilityEntry),
+    /// The capability is only in the older snapshot
+    Disappeared(&'a CapabilityEntry),
+    /// The capability is in both snapshots, but it moved or its contents differ
+    Changed {
+        before: &'a CapabilityEntry,
+        after: &'a CapabilityEntry,
+    },
+}
+
+/// The capability list and extended capability list of a function, captured so they can be compared with a later
+/// capture, e.g. to notice that a firmware update altered a device's capabilities.
+///
+/// ### Note
+/// The contents of every capability are captured as-is, including status bits the device may set at any time. To
+/// compare only the configuration of two snapshots, mask those out of [`CapabilityEntry::dwords`] first.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CapabilitySnapshot {
+    entries: Vec<CapabilityEntry>,
+}
+
+impl CapabilitySnapshot {
+    /// Capture the capabilities of the function `header` belongs to. The extended capabilities are only
+    /// captured if `access` can reach them.
+    pub fn capture(access: &impl ConfigRegionAccess, header: &PciHeader) -> CapabilitySnapshot {
+        let mut entries = Vec::new();
+        for capability in CapabilityIterator::for_header(header, access) {
+            let mut dwords: Vec<u32> = capability.dwords(access).collect();
+            dwords[0].set_bits(8..16, 0);
+            entries.push(CapabilityEntry {
+                kind: CapabilityKind::Standard(capability.id()),
+                offset: capability.address().offset,
+                dwords,
+            });
+        }
+        for capability in ExtendedCapabilityIterator::new(header.address(), 0x100, access) {
+            let mut dwords: Vec<u32> = capability.dwords(access).collect();
+            dwords[0].set_bits(20..32, 0);
+            entries.push(CapabilityEntry {
+                kind: CapabilityKind::Extended {
+                    id: capability.id(),
+                    version: capability.version(),
+                },
+                offset: capability.address().offset,
+                dwords,
+            });
+        }
+        CapabilitySnapshot { entries }
+    }
+
+    /// The captured capabilities, in the order they were found in their lists
+    pub fn entries(&self) -> &[CapabilityEntry] {
+        &self.entries
+    }
+
+    /// Find the differences between this snapshot and a `newer` one. Capabilities are matched up by their kind
+    /// rather than their offset, so a capability that moved is reported as changed. If a kind appears more than
+    /// once (e.g. vendor-specific capabilities), the first in each snapshot are matched up, then the second, and
+    /// so on.
+    pub fn diff<'a>(&'a self, newer: &'a CapabilitySnapshot) -> Vec<CapabilityChange<'a>> {
+        let mut changes = Vec::new();
+        for (index, before) in self.entries.iter().enumerate() {
+            match matching(&self.entries[..index], before, &newer.entries) {
+                Some(after) if after != before => {
+                    changes.push(CapabilityChange::Changed { before, after })
+                }
+                Some(_) => (),
+                None => changes.push(CapabilityChange::Disappeared(before)),
+            }
+        }
+        for (index, after) in newer.entries.iter().enumerate() {
+            if matching(&newer.entries[..index], after, &self.entries).is_none() {
+                changes.push(CapabilityChange::Appeared(after));
+            }
+        }
+        changes
+    }
+}
+
+/// Find the entry in `other` that matches `entry`, where `earlier` are the entries before it in its own snapshot
+fn matching<'a>(
+    earlier: &[CapabilityEntry],
+    entry: &CapabilityEntry,
+    other: &'a [CapabilityEntry],
+) -> Option<&'a CapabilityEntry> {
+    let occurrence = earlier.iter().filter(|e| e.kind == entry.kind).count();
+    other
+        .iter()
+        .filter(|e| e.kind == entry.kind)
+        .nth(occurrence)
+}
@@ -0,0 +1,30 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess, DeviceId, VendorId};
+use bit_field::BitField;
+
+/// Bridge subsystem vendor/device ID capability. Type-1 headers have no Subsystem ID fields, so PCI-to-PCI
+/// bridges that need to identify the board they are on expose them through this capability instead.
+#[derive(Debug, Clone)]
+pub struct BridgeSubsystemCapability {
+    address: PciCapabilityAddress,
+}
+
+impl BridgeSubsystemCapability {
+    pub(crate) fn new(address: PciCapabilityAddress) -> BridgeSubsystemCapability {
+        BridgeSubsystemCapability { address }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// Read the Subsystem Vendor ID and Subsystem ID of the bridge
+    pub fn subsystem(&self, access: &impl ConfigRegionAccess) -> (VendorId, DeviceId) {
+        let reg = unsafe { access.read(self.address.address, self.address.offset + 0x4) };
+        (
+            reg.get_bits(0..16) as VendorId,
+            reg.get_bits(16..32) as DeviceId,
+        )
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    capability::{CapabilityId, CapabilityIterator, ExtendedCapabilityId, PciCapability},
+    ConfigRegionAccess, PciHeader,
+};
+use bit_field::BitField;
+
+/// Ways in which a capability list can be malformed, as reported by [`validate_capabilities`]. Each variant
+/// carries the offset of the capability that was found to be bad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapabilityError {
+    /// The capability starts inside the header, or doesn't fit in the configuration space it belongs to.
+    /// `length` is the minimum length of the capability, in bytes.
+    OutOfBounds { offset: u16, length: u16 },
+    /// The capability overlaps a capability found earlier in the list. This also catches lists that link back
+    /// on themselves.
+    Overlapping { offset: u16 },
+}
+
+/// A capability that couldn't be parsed by [`parse_capabilities`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapabilityParseError {
+    /// The capability starts inside the header, or its registers run past the end of the configuration space.
+    /// `length` is the minimum length of a capability with this ID, in bytes.
+    OutOfBounds {
+        offset: u16,
+        id: CapabilityId,
+        length: u16,
+    },
+}
+
+/// Parse the capability list of a function like [`crate::EndpointHeader::capabilities`] does, but report
+/// capabilities that are misplaced instead of handing out capabilities whose registers can't be trusted. The
+/// iterator ends after the first error, as the next pointer of a bad capability can't be trusted either.
+pub fn parse_capabilities<'a>(
+    access: &'a impl ConfigRegionAccess,
+    header: &PciHeader,
+) -> impl Iterator<Item = Result<PciCapability, CapabilityParseError>> + 'a {
+    let mut capabilities = CapabilityIterator::for_header(header, access);
+    core::iter::from_fn(move || loop {
+        match capabilities.try_next_raw()? {
+            Ok((address, data)) => {
+                if let Some(capability) = PciCapability::parse(
+                    data.get_bits(0..8) as u8,
+                    address,
+                    data.get_bits(16..32) as u16,
+                ) {
+                    return Some(Ok(capability));
+                }
+            }
+            Err(error) => return Some(Err(error)),
+        }
+    })
+}
+
+/// Walk the capability list and extended capability list of a function, and check that every capability fits in
+/// the configuration space along with the registers it must have, and doesn't overlap any other capability. This
+/// is meant for checking configuration spaces that can't be trusted (e.g. ones read from firmware images) before
+/// using them.
+///
+/// Misaligned pointers are not rejected: their reserved bottom two bits are masked off before they are followed,
+/// as for [`crate::EndpointHeader::capabilities`].
+///
+/// The extended capability list is only checked if the function has an extended configuration space, and `access`
+/// can reach it (see [`ConfigRegionAccess::supports_extended`]).
+pub fn validate_capabilities(
+    access: &impl ConfigRegionAccess,
+    header: &PciHeader,
+) -> Result<(), CapabilityError> {
+    let address = header.address();
+    let read = |offset: u16| unsafe { access.read(address, offset) };
+    /*
+     * One bit for every dword of the configuration space, set once a capability has claimed it.
+     */
+    let mut claimed = [0u64; 0x1000 / 4 / 64];
+    let mut claim = |offset: u16, length: u16| {
+        for dword in (offset / 4)..(offset + length).div_ceil(4) {
+            let (word, bit) = (dword as usize / 64, dword as usize % 64);
+            if claimed[word].get_bit(bit) {
+                return Err(CapabilityError::Overlapping { offset });
+            }
+            claimed[word].set_bit(bit, true);
+        }
+        Ok(())
+    };
+
+    let mut capabilities = CapabilityIterator::for_header(header, access);
+    while let Some(capability) = capabilities.try_next_raw() {
+        let (address, data) = capability.map_err(|error| match error {
+            CapabilityParseError::OutOfBounds { offset, length, .. } => {
+                CapabilityError::OutOfBounds { offset, length }
+            }
+        })?;
+        let length = standard_length(CapabilityId::from(data.get_bits(0..8) as u8), data);
+        claim(address.offset, length)?;
+    }
+
+    let mut offset = 0x100;
+    while access.supports_extended() {
+        let data = read(offset);
+        if data == 0 || data == 0xffffffff {
+            break;
+        }
+        let length = extended_length(ExtendedCapabilityId::from(data.get_bits(0..16) as u16));
+        if offset < 0x100 || offset + length > 0x1000 {
+            return Err(CapabilityError::OutOfBounds { offset, length });
+        }
+        claim(offset, length)?;
+
+        offset = data.get_bits(20..32) as u16 & !0x3;
+        if offset == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// The minimum length of a standard capability in bytes, including its header. `header` is the first dword of
+/// the capability, which some capabilities use to describe which registers they have.
+pub(crate) fn standard_length(id: CapabilityId, header: u32) -> u16 {
+    match id {
+        CapabilityId::PowerManagement => 0x8,
+        CapabilityId::AcceleratedGraphicsPort => 0xc,
+        CapabilityId::VitalProductData => 0x8,
+        CapabilityId::Msi => {
+            let is_64bit = header.get_bit(23);
+            let per_vector_masking = header.get_bit(24);
+            match (is_64bit, per_vector_masking) {
+                (false, false) => 0xa,
+                (true, false) => 0xe,
+                (false, true) => 0x14,
+                (true, true) => 0x18,
+            }
+        }
+        CapabilityId::PciX => 0x8,
+        /*
+         * Vendor-specific capabilities hold their own length in the byte after the next pointer.
+         */
+        CapabilityId::VendorSpecific => (header.get_bits(16..24) as u16).max(3),
+        CapabilityId::BridgeSubsystemVendorId => 0x8,
+        CapabilityId::PciExpress => {
+            if header.get_bits(16..20) >= 2 {
+                0x3c
+            } else {
+                0x24
+            }
+        }
+        CapabilityId::MsiX => 0xc,
+        CapabilityId::AdvancedFeatures => 0x6,
+        _ => 0x4,
+    }
+}
+
+/// The minimum length of an extended capability in bytes, including its header
+pub(crate) fn extended_length(id: ExtendedCapabilityId) -> u16 {
+    match id {
+        ExtendedCapabilityId::AdvancedErrorReporting => 0x2c,
+        ExtendedCapabilityId::DeviceSerialNumber => 0xc,
+        ExtendedCapabilityId::AlternativeRoutingId => 0x8,
+        ExtendedCapabilityId::LatencyToleranceReporting => 0x8,
+        ExtendedCapabilityId::AccessControlServices => 0x8,
+        /*
+         * Switch and root ports also have the MC_Overlay_BAR at 0x28, but endpoints don't.
+         */
+        ExtendedCapabilityId::Multicast => 0x28,
+        ExtendedCapabilityId::VendorSpecific => 0x8,
+        /*
+         * The Steering Tag table can follow the control register, but its size isn't covered here.
+         */
+        ExtendedCapabilityId::TphRequester => 0xc,
+        ExtendedCapabilityId::DataLinkFeature => 0xc,
+        /*
+         * This covers the registers up to the second retimer's parity mismatch status. The Lane Equalization
+         * Control registers after them take a byte per lane, so their length depends on the link width.
+         */
+        ExtendedCapabilityId::PhysicalLayer16 => 0x20,
+        _ => 0x4,
+    }
+}
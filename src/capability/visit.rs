@@ -0,0 +1,92 @@
+use crate::{
+    capability::{
+        AdvancedFeaturesCapability, BridgeSubsystemCapability, CapabilityId, CapabilityIterator,
+        EnhancedAllocationCapability, MsiCapability, PciCapability, PciCapabilityAddress,
+        PciXCapability, PcieCapability, PowerManagementCapability, VpdCapability,
+    },
+    ConfigRegionAccess, PciHeader,
+};
+
+/// Handles the capabilities found by [`walk_capabilities`]. Every method does nothing by default, except that the
+/// methods for specific capabilities hand them to [`CapabilityVisitor::visit_unknown`], so an implementation only
+/// needs the methods for the capabilities it is interested in.
+pub trait CapabilityVisitor {
+    fn visit_power_management(&mut self, capability: PowerManagementCapability) {
+        self.visit_unknown(CapabilityId::PowerManagement, capability.address().offset);
+    }
+
+    fn visit_vpd(&mut self, capability: VpdCapability) {
+        self.visit_unknown(CapabilityId::VitalProductData, capability.address().offset);
+    }
+
+    fn visit_msi(&mut self, capability: MsiCapability) {
+        self.visit_unknown(CapabilityId::Msi, capability.address().offset);
+    }
+
+    fn visit_pcix(&mut self, capability: PciXCapability) {
+        self.visit_unknown(CapabilityId::PciX, capability.address().offset);
+    }
+
+    fn visit_bridge_subsystem(&mut self, capability: BridgeSubsystemCapability) {
+        self.visit_unknown(
+            CapabilityId::BridgeSubsystemVendorId,
+            capability.address().offset,
+        );
+    }
+
+    fn visit_pcie(&mut self, capability: PcieCapability) {
+        self.visit_unknown(CapabilityId::PciExpress, capability.address().offset);
+    }
+
+    /// MSI-X capabilities aren't decoded by this crate, so only their location is passed on
+    fn visit_msix(&mut self, address: PciCapabilityAddress) {
+        self.visit_unknown(CapabilityId::MsiX, address.offset);
+    }
+
+    fn visit_advanced_features(&mut self, capability: AdvancedFeaturesCapability) {
+        self.visit_unknown(CapabilityId::AdvancedFeatures, capability.address().offset);
+    }
+
+    fn visit_enhanced_allocation(&mut self, capability: EnhancedAllocationCapability) {
+        self.visit_unknown(
+            CapabilityId::EnhancedAllocation,
+            capability.address().offset,
+        );
+    }
+
+    /// Called for every capability without a method of its own, and for those whose method isn't implemented.
+    /// `offset` is the location of the capability in configuration space.
+    fn visit_unknown(&mut self, _id: CapabilityId, _offset: u16) {}
+}
+
+/// Walk the capability list of a function, and pass each capability to the matching method of `visitor`. This
+/// works for any header type, finding the capability list where [`parse_capabilities`](super::parse_capabilities)
+/// does.
+pub fn walk_capabilities(
+    access: &impl ConfigRegionAccess,
+    header: &PciHeader,
+    visitor: &mut impl CapabilityVisitor,
+) {
+    for capability in CapabilityIterator::for_header(header, access) {
+        match capability {
+            PciCapability::PowerManagement(capability) => {
+                visitor.visit_power_management(capability)
+            }
+            PciCapability::VitalProductData(capability) => visitor.visit_vpd(capability),
+            PciCapability::Msi(capability) => visitor.visit_msi(capability),
+            PciCapability::PciX(capability) => visitor.visit_pcix(capability),
+            PciCapability::BridgeSubsystemVendorId(capability) => {
+                visitor.visit_bridge_subsystem(capability)
+            }
+            PciCapability::PciExpress(capability) => visitor.visit_pcie(capability),
+            PciCapability::MsiX(address) => visitor.visit_msix(address),
+            PciCapability::AdvancedFeatures(capability) => {
+                visitor.visit_advanced_features(capability)
+            }
+            PciCapability::EnhancedAllocation(capability) => {
+                visitor.visit_enhanced_allocation(capability)
+            }
+            other => visitor.visit_unknown(other.id(), other.address().offset),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{capability::PciCapabilityAddress, ConfigRegionAccess, Timeout};
+use bit_field::BitField;
+
+/// Vital Product Data capability. VPD (e.g. part and serial numbers) lives in storage on the device, and is
+/// accessed a dword at a time through a window made of an address register and a data register.
+#[derive(Debug, Clone)]
+pub struct VpdCapability {
+    address: PciCapabilityAddress,
+}
+
+impl VpdCapability {
+    pub(crate) fn new(address: PciCapabilityAddress) -> VpdCapability {
+        VpdCapability { address }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// Read the dword at `offset` in the device's VPD. Only the lower 15 bits of `offset` are used.
+    ///
+    /// The device signals that the data is ready by setting a flag, which is polled up to `max_iterations`
+    /// times before giving up with [`Timeout`].
+    pub fn read(
+        &self,
+        access: &impl ConfigRegionAccess,
+        offset: u16,
+        max_iterations: usize,
+    ) -> Result<u32, Timeout> {
+        self.set_address(access, offset, false);
+        self.wait_for_flag(access, true, max_iterations)?;
+        Ok(unsafe { access.read(self.address.address, self.address.offset + 0x4) })
+    }
+
+    /// Write `value` to the dword at `offset` in the device's VPD. Only the lower 15 bits of `offset` are used.
+    ///
+    /// The device signals that the write has completed by clearing a flag, which is polled up to
+    /// `max_iterations` times before giving up with [`Timeout`].
+    pub fn write(
+        &self,
+        access: &impl ConfigRegionAccess,
+        offset: u16,
+        value: u32,
+        max_iterations: usize,
+    ) -> Result<(), Timeout> {
+        unsafe { access.write(self.address.address, self.address.offset + 0x4, value) };
+        self.set_address(access, offset, true);
+        self.wait_for_flag(access, false, max_iterations)
+    }
+
+    fn set_address(&self, access: &impl ConfigRegionAccess, offset: u16, flag: bool) {
+        let mut reg = unsafe { access.read(self.address.address, self.address.offset) };
+        reg.set_bits(16..31, offset.get_bits(0..15) as u32);
+        reg.set_bit(31, flag);
+        unsafe { access.write(self.address.address, self.address.offset, reg) };
+    }
+
+    fn wait_for_flag(
+        &self,
+        access: &impl ConfigRegionAccess,
+        flag: bool,
+        max_iterations: usize,
+    ) -> Result<(), Timeout> {
+        (0..max_iterations)
+            .map(|_| unsafe { access.read(self.address.address, self.address.offset) })
+            .find(|reg| reg.get_bit(31) == flag)
+            .map(|_| ())
+            .ok_or(Timeout)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    capability::{ExtendedCapabilityId, PciCapabilityAddress, PciExtendedCapability},
+    ConfigRegionAccess,
+};
+use bit_field::BitField;
+
+/// Vendor-Specific extended capability, which holds registers defined by the vendor of the function. The VSEC ID
+/// and revision in its header identify the layout of the rest of the capability, which is left undecoded.
+///
+/// ### Note
+/// VSEC IDs are only meaningful together with the Vendor ID of the function, as each vendor allocates its own.
+#[derive(Debug, Clone)]
+pub struct VsecCapability {
+    address: PciCapabilityAddress,
+}
+
+impl VsecCapability {
+    /// Interpret an extended capability as a Vendor-Specific extended capability. Returns `None` if it is a
+    /// different capability.
+    pub fn new(capability: &PciExtendedCapability) -> Option<VsecCapability> {
+        match capability.id() {
+            ExtendedCapabilityId::VendorSpecific => Some(VsecCapability {
+                address: capability.address().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Location of the capability in configuration space
+    #[inline]
+    pub fn address(&self) -> &PciCapabilityAddress {
+        &self.address
+    }
+
+    /// The vendor-defined ID of the layout of this capability
+    pub fn vsec_id(&self, access: &impl ConfigRegionAccess) -> u16 {
+        self.vsec_header(access).get_bits(0..16) as u16
+    }
+
+    /// The vendor-defined version of the layout of this capability
+    pub fn vsec_revision(&self, access: &impl ConfigRegionAccess) -> u8 {
+        self.vsec_header(access).get_bits(16..20) as u8
+    }
+
+    /// The length of the whole capability in bytes, including both headers
+    pub fn vsec_length(&self, access: &impl ConfigRegionAccess) -> u16 {
+        self.vsec_header(access).get_bits(20..32) as u16
+    }
+
+    /// Iterate over the bytes of the capability after its headers. A length that runs past the end of the
+    /// configuration space is cut short there.
+    pub fn body<'a>(&self, access: &'a impl ConfigRegionAccess) -> impl Iterator<Item = u8> + 'a {
+        let address = self.address.address;
+        let start = self.address.offset + 0x8;
+        let end = (self.address.offset + self.vsec_length(access)).min(0x1000);
+        (start..end).map(move |offset| unsafe { access.read_u8(address, offset) })
+    }
+
+    fn vsec_header(&self, access: &impl ConfigRegionAccess) -> u32 {
+        unsafe { access.read(self.address.address, self.address.offset + 0x4) }
+    }
+}
@@ -24,6 +24,7 @@ pub enum DeviceType {
     AtaController,
     SataController,
     SasController,
+    NvmController,
     OtherMassStorageController,
 
     /*
@@ -196,6 +197,7 @@ impl From<(BaseClass, SubClass)> for DeviceType {
             (0x01, 0x05) => DeviceType::AtaController,
             (0x01, 0x06) => DeviceType::SataController,
             (0x01, 0x07) => DeviceType::SasController,
+            (0x01, 0x08) => DeviceType::NvmController,
             (0x01, 0x80) => DeviceType::OtherMassStorageController,
 
             (0x02, 0x00) => DeviceType::EthernetController,
@@ -309,6 +311,117 @@ impl From<(BaseClass, SubClass)> for DeviceType {
     }
 }
 
+impl DeviceType {
+    /// Decode the Interface (also known as the Programming Interface or ProgIF) of a device of this type. The
+    /// meaning of the Interface depends on the device type, so this returns `DeviceInterface::Unknown` for types
+    /// that don't define interfaces this crate knows about.
+    pub fn decode_interface(&self, interface: Interface) -> DeviceInterface {
+        let decoded = match self {
+            DeviceType::IdeController => Some(DeviceInterface::Ide(IdeInterface::new(interface))),
+            DeviceType::SataController => SataType::try_from(interface)
+                .ok()
+                .map(DeviceInterface::Sata),
+            DeviceType::NvmController => {
+                NvmType::try_from(interface).ok().map(DeviceInterface::Nvm)
+            }
+            DeviceType::UsbController => {
+                UsbType::try_from(interface).ok().map(DeviceInterface::Usb)
+            }
+            _ => None,
+        };
+        decoded.unwrap_or(DeviceInterface::Unknown(interface))
+    }
+}
+
+/// The programming interface of a device, decoded by `DeviceType::decode_interface`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeviceInterface {
+    Ide(IdeInterface),
+    Sata(SataType),
+    Nvm(NvmType),
+    Usb(UsbType),
+    /// An interface not defined for the device type, or not known to this crate
+    Unknown(Interface),
+}
+
+/// The programming interface of an IDE controller (devices of type `DeviceType::IdeController`). Unlike most
+/// interfaces, this is a set of flags describing the modes each channel supports.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IdeInterface(Interface);
+
+impl IdeInterface {
+    pub fn new(interface: Interface) -> IdeInterface {
+        IdeInterface(interface)
+    }
+
+    /// Is the primary channel operating in PCI native mode (rather than ISA compatibility mode)?
+    pub fn primary_native(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    /// Can the primary channel be switched between compatibility and native mode?
+    pub fn primary_switchable(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    /// Is the secondary channel operating in PCI native mode (rather than ISA compatibility mode)?
+    pub fn secondary_native(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// Can the secondary channel be switched between compatibility and native mode?
+    pub fn secondary_switchable(&self) -> bool {
+        self.0 & 0x08 != 0
+    }
+
+    /// Does the controller support bus mastering DMA?
+    pub fn bus_mastering(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+}
+
+/// The different register-level programming interfaces defined for SATA controllers (devices of type
+/// `DeviceType::SataController`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SataType {
+    VendorSpecific,
+    Ahci,
+    SerialStorageBus,
+}
+
+impl TryFrom<Interface> for SataType {
+    type Error = ();
+
+    fn try_from(interface: Interface) -> Result<Self, Self::Error> {
+        match interface {
+            0x00 => Ok(SataType::VendorSpecific),
+            0x01 => Ok(SataType::Ahci),
+            0x02 => Ok(SataType::SerialStorageBus),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The different register-level programming interfaces defined for non-volatile memory controllers (devices of
+/// type `DeviceType::NvmController`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NvmType {
+    Nvmhci,
+    Nvme,
+}
+
+impl TryFrom<Interface> for NvmType {
+    type Error = ();
+
+    fn try_from(interface: Interface) -> Result<Self, Self::Error> {
+        match interface {
+            0x01 => Ok(NvmType::Nvmhci),
+            0x02 => Ok(NvmType::Nvme),
+            _ => Err(()),
+        }
+    }
+}
+
 /// The different register-level programming interfaces defined for USB controllers (devices of type
 /// `DeviceType::UsbController`).
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -0,0 +1,200 @@
+use crate::{
+    read_header_type, ConfigRegionAccess, HeaderType, PciAddress, PciHeader, PciPciBridgeHeader,
+};
+use bit_field::BitField;
+
+/// The order in which [`enumerate`] visits the functions of a segment
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum EnumerationOrder {
+    /// Visit the buses behind a bridge as soon as the bridge itself is found, before carrying on with the rest of
+    /// its bus. This doesn't allocate.
+    DepthFirst,
+    /// Visit every function on a bus before any of the buses behind it, which groups functions by bus in the
+    /// same way `lspci` does. This keeps a queue of buses still to be scanned, so it needs `alloc`.
+    #[cfg(feature = "alloc")]
+    BreadthFirst,
+}
+
+/// A set of function numbers of a single device, as returned by [`present_functions`]. Bit `n` is set if
+/// function `n` is in the set.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct FunctionMask(u8);
+
+impl FunctionMask {
+    pub fn new(value: u8) -> Self {
+        FunctionMask(value)
+    }
+
+    /// Raw value of the mask
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Will be `true` if `function` is in the set. Function numbers above `7` are never in the set.
+    pub fn contains(&self, function: u8) -> bool {
+        function < 8 && self.0.get_bit(function as usize)
+    }
+
+    /// Will be `true` if no functions are in the set
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the function numbers in the set, from lowest to highest
+    pub fn iter(&self) -> impl Iterator<Item = u8> {
+        let mask = *self;
+        (0..8).filter(move |&function| mask.contains(function))
+    }
+}
+
+impl core::fmt::Debug for FunctionMask {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Find which functions of the device at `device` are present. The function number of `device` is ignored.
+///
+/// A function counts as present if `access` says it exists and its Vendor ID doesn't read as `0xffff`, which is
+/// what reads of a missing function return on most platforms. Function 0 must be present for any of the others to
+/// be, and the others are only probed if function 0 reports that the device has multiple functions. Functions of a
+/// multi-function device don't have to be numbered contiguously, so every function number is checked.
+pub fn present_functions(access: &impl ConfigRegionAccess, device: PciAddress) -> FunctionMask {
+    let function_zero = device.function_zero();
+    if !is_present(access, function_zero) {
+        return FunctionMask(0);
+    }
+    if !read_header_type(access, function_zero).1 {
+        return FunctionMask(1);
+    }
+
+    let mut mask = FunctionMask(1);
+    for function in 1..8 {
+        let address = PciAddress::new(device.segment(), device.bus(), device.device(), function);
+        mask.0
+            .set_bit(function as usize, is_present(access, address));
+    }
+    mask
+}
+
+fn is_present(access: &impl ConfigRegionAccess, address: PciAddress) -> bool {
+    access.function_exists(address) && PciHeader::new(address).id(access).0 != 0xffff
+}
+
+/// Find every function in `segment` by scanning from bus 0 and following PCI-to-PCI bridges, and call `visit`
+/// with each one found.
+///
+/// A bridge is only followed if the buses it claims lie within the range claimed by the bridge above it, and its
+/// secondary bus is higher than the bus it sits on. Each bus is scanned at most once. This keeps a misprogrammed
+/// bridge from making the scan loop or wander onto buses that belong to another part of the hierarchy.
+///
+/// Functions with a header type this crate doesn't know about are not passed to `visit`, as nothing past the
+/// common part of their header can be trusted. Returns the number of functions that were skipped for this reason.
+pub fn enumerate(
+    access: &impl ConfigRegionAccess,
+    segment: u16,
+    order: EnumerationOrder,
+    mut visit: impl FnMut(PciAddress),
+) -> usize {
+    let mut scanned = [0u64; 4];
+    let mut skipped = 0;
+    claim(&mut scanned, 0);
+    match order {
+        EnumerationOrder::DepthFirst => depth_first(
+            access,
+            segment,
+            (0, 255),
+            &mut scanned,
+            &mut skipped,
+            &mut visit,
+        ),
+        #[cfg(feature = "alloc")]
+        EnumerationOrder::BreadthFirst => {
+            let mut queue = alloc::collections::VecDeque::new();
+            queue.push_back((0, 255));
+            while let Some(buses) = queue.pop_front() {
+                for device in 0..32 {
+                    for child in
+                        scan_device(access, segment, buses, device, &mut skipped, &mut visit)
+                            .iter()
+                            .flatten()
+                    {
+                        if claim(&mut scanned, child.0) {
+                            queue.push_back(*child);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    skipped
+}
+
+/// Scan the bus `buses.0`, which is the first of the range of buses `buses.0..=buses.1`, then each bus behind it
+fn depth_first(
+    access: &impl ConfigRegionAccess,
+    segment: u16,
+    buses: (u8, u8),
+    scanned: &mut [u64; 4],
+    skipped: &mut usize,
+    visit: &mut impl FnMut(PciAddress),
+) {
+    for device in 0..32 {
+        for child in scan_device(access, segment, buses, device, skipped, visit)
+            .iter()
+            .flatten()
+        {
+            if claim(scanned, child.0) {
+                depth_first(access, segment, *child, scanned, skipped, visit);
+            }
+        }
+    }
+}
+
+/// Visit every function of a device on the bus `buses.0`, and return the bus ranges behind any of them that are
+/// bridges and can be followed, indexed by function number. Functions with an unknown header type are counted
+/// in `skipped` instead of being visited.
+fn scan_device(
+    access: &impl ConfigRegionAccess,
+    segment: u16,
+    buses: (u8, u8),
+    device: u8,
+    skipped: &mut usize,
+    visit: &mut impl FnMut(PciAddress),
+) -> [Option<(u8, u8)>; 8] {
+    let mut children = [None; 8];
+    let (bus, limit) = buses;
+
+    for function in present_functions(access, PciAddress::new(segment, bus, device, 0)).iter() {
+        let address = PciAddress::new(segment, bus, device, function);
+        let (header_type, _) = read_header_type(access, address);
+        if let HeaderType::Unknown(_) = header_type {
+            *skipped += 1;
+            continue;
+        }
+        visit(address);
+
+        if header_type != HeaderType::PciPciBridge {
+            continue;
+        }
+        let claimed = PciPciBridgeHeader::from_header(PciHeader::new(address), access)
+            .and_then(|bridge| bridge.claimed_buses(access));
+        if let Some(claimed) = claimed {
+            if *claimed.start() > bus && *claimed.end() <= limit {
+                children[function as usize] = Some((*claimed.start(), *claimed.end()));
+            }
+        }
+    }
+    children
+}
+
+/// Mark `bus` as scanned. Returns `false` if it had already been scanned.
+fn claim(scanned: &mut [u64; 4], bus: u8) -> bool {
+    let (word, bit) = (bus as usize / 64, bus as usize % 64);
+    if scanned[word].get_bit(bit) {
+        return false;
+    }
+    scanned[word].set_bit(bit, true);
+    true
+}
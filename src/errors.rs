@@ -0,0 +1,78 @@
+use crate::{
+    capability::{
+        AdvancedErrorReportingCapability, CorrectableErrors, ExtendedCapabilityId, PciCapability,
+        PciExtendedCapability, PcieCapability, UncorrectableErrors,
+    },
+    ConfigRegionAccess, EndpointHeader, PciAddress, PciHeader, StatusRegister,
+};
+
+/// The error reporting mechanisms of a function, as found by [`EndpointHeader::error_reporting`]. Each is `None`
+/// if the function doesn't have the corresponding capability.
+#[derive(Debug, Clone)]
+pub struct ErrorReporting {
+    address: PciAddress,
+    pub pcie: Option<PcieCapability>,
+    pub aer: Option<AdvancedErrorReportingCapability>,
+    /// The Downstream Port Containment capability. It isn't touched by [`ErrorReporting::clear_all`] or
+    /// [`ErrorReporting::enable_all`]: clearing its trigger status takes the port out of containment, and whether
+    /// to enable containment at all is up to the caller.
+    pub dpc: Option<PciExtendedCapability>,
+}
+
+impl ErrorReporting {
+    pub(crate) fn new(header: &EndpointHeader, access: &impl ConfigRegionAccess) -> ErrorReporting {
+        let pcie = header
+            .capabilities(access)
+            .find_map(|capability| match capability {
+                PciCapability::PciExpress(pcie) => Some(pcie),
+                _ => None,
+            });
+        /*
+         * Only PCI Express functions have an extended configuration space.
+         */
+        let (mut aer, mut dpc) = (None, None);
+        if pcie.is_some() {
+            for capability in header.extended_capabilities(access) {
+                match capability.id() {
+                    ExtendedCapabilityId::AdvancedErrorReporting => {
+                        aer = AdvancedErrorReportingCapability::new(&capability)
+                    }
+                    ExtendedCapabilityId::DownstreamPortContainment => dpc = Some(capability),
+                    _ => (),
+                }
+            }
+        }
+
+        ErrorReporting {
+            address: header.header().address(),
+            pcie,
+            aer,
+            dpc,
+        }
+    }
+
+    /// Clear every error that has been recorded: the error bits of the Status register, the error bits of the
+    /// PCI Express Device Status register, and the AER error status registers.
+    pub fn clear_all(&self, access: &impl ConfigRegionAccess) {
+        PciHeader::new(self.address).read_and_clear_status(access, StatusRegister::RW1C_MASK);
+        if let Some(pcie) = &self.pcie {
+            pcie.clear_device_errors(access);
+        }
+        if let Some(aer) = &self.aer {
+            aer.clear_uncorrectable_status(access, UncorrectableErrors::all());
+            aer.clear_correctable_status(access, CorrectableErrors::all());
+        }
+    }
+
+    /// Enable the reporting of every kind of error: turn on error reporting in the PCI Express Device Control
+    /// register, and unmask every error in the AER mask registers. Severities are left as they are.
+    pub fn enable_all(&self, access: &impl ConfigRegionAccess) {
+        if let Some(pcie) = &self.pcie {
+            pcie.set_error_reporting(access, true);
+        }
+        if let Some(aer) = &self.aer {
+            aer.set_uncorrectable_mask(access, UncorrectableErrors::empty());
+            aer.set_correctable_mask(access, CorrectableErrors::empty());
+        }
+    }
+}
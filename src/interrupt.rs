@@ -0,0 +1,66 @@
+use crate::PciAddress;
+use core::convert::TryFrom;
+
+/// One of the four legacy INTx interrupt pins of a function, as reported by its Interrupt Pin register
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum InterruptPin {
+    IntA,
+    IntB,
+    IntC,
+    IntD,
+}
+
+impl InterruptPin {
+    /// The value of the Interrupt Pin register for this pin (`1` for INTA# to `4` for INTD#)
+    pub fn register_value(&self) -> u8 {
+        *self as u8 + 1
+    }
+
+    fn from_index(index: u8) -> InterruptPin {
+        match index % 4 {
+            0 => InterruptPin::IntA,
+            1 => InterruptPin::IntB,
+            2 => InterruptPin::IntC,
+            _ => InterruptPin::IntD,
+        }
+    }
+}
+
+/// Decode the value of an Interrupt Pin register. Fails for `0`, which means the function doesn't use an
+/// interrupt pin, and for the reserved values above `4`.
+impl TryFrom<u8> for InterruptPin {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1..=4 => Ok(InterruptPin::from_index(value - 1)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Find the pin that `pin` of the device numbered `device` is connected to on the upstream side of the bridge it
+/// is behind. Bridges rotate the pins of the devices on their secondary bus by the device number, so that devices
+/// using INTA# don't all end up sharing the same line: e.g. INTA# of device 1 comes out on INTB#.
+pub fn swizzle_interrupt_pin(pin: InterruptPin, device: u8) -> InterruptPin {
+    InterruptPin::from_index(pin as u8 + device % 4)
+}
+
+/// Follow `pin` of the function at `address` up through a chain of bridges, applying
+/// [`swizzle_interrupt_pin`] at each one. `bridges` lists the addresses of the bridges between the function and
+/// the root bus, starting with the one directly above the function.
+///
+/// Returns the address of the device on the root bus that the interrupt comes out of (the last bridge, or
+/// `address` itself if `bridges` is empty) along with the pin it comes out on. This is the pair to look up in the
+/// platform's interrupt routing tables (e.g. ACPI `_PRT`).
+pub fn swizzle_interrupt_path(
+    address: PciAddress,
+    pin: InterruptPin,
+    bridges: impl IntoIterator<Item = PciAddress>,
+) -> (PciAddress, InterruptPin) {
+    bridges
+        .into_iter()
+        .fold((address, pin), |(below, pin), bridge| {
+            (bridge, swizzle_interrupt_pin(pin, below.device()))
+        })
+}
@@ -1,14 +1,49 @@
 #![no_std]
 
+#[cfg(any(test, feature = "alloc"))]
+extern crate alloc;
+
+pub mod access;
+mod buffer;
 pub mod capability;
 pub mod device_type;
+mod enumerate;
+mod errors;
+mod interrupt;
+mod mapped;
+mod quirk;
 mod register;
+mod reset;
+mod rom;
+mod snapshot;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use register::{DevselTiming, StatusRegister};
+pub use buffer::ConfigBuffer;
+pub use enumerate::{enumerate, present_functions, EnumerationOrder, FunctionMask};
+pub use errors::ErrorReporting;
+pub use interrupt::{swizzle_interrupt_path, swizzle_interrupt_pin, InterruptPin};
+pub use mapped::{MappedBar, OutOfBounds};
+pub use quirk::Quirk;
+pub use register::{
+    command_status_dword, CacheLineSize, CommandRegister, CommandRegisterBuilder, ConfigRegister,
+    DevselTiming, InvalidCacheLineSize, StatusRegister,
+};
+pub use reset::{ResetError, ResetMethod};
+pub use rom::{ExpansionRom, RomError, RomImage};
+pub use snapshot::ConfigSnapshot;
 
-use crate::capability::CapabilityIterator;
+use crate::capability::{
+    BarEquivalent, CapabilityId, CapabilityIterator, CapabilityOffset, EaProperty,
+    ExtendedCapabilityId, ExtendedCapabilityIterator, PciCapability,
+};
+use crate::device_type::DeviceType;
 use bit_field::BitField;
-use core::fmt;
+use core::{
+    convert::TryFrom,
+    fmt,
+    ops::{Range, RangeInclusive},
+};
 
 /// PCIe supports 65536 segments, each with 256 buses, each with 32 slots, each with 8 possible functions. We cram this into a `u32`:
 ///
@@ -18,7 +53,13 @@ use core::fmt;
 ///  |            segment            |      bus      | device  | func |
 ///  +-------------------------------+---------------+---------+------+
 /// ```
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+///
+/// For devices using Alternative Routing-ID Interpretation, the device and function fields together form an 8-bit
+/// function number (see [`PciAddress::new_ari`]).
+///
+/// Both the `Display` and `Debug` representations use the canonical `segment:bus:device.function` form, e.g.
+/// `0000:00:1f.2`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct PciAddress(u32);
 
 impl PciAddress {
@@ -31,6 +72,28 @@ impl PciAddress {
         PciAddress(result)
     }
 
+    /// Create the address of a function of a device using Alternative Routing-ID Interpretation (ARI). Under ARI,
+    /// the device number is always `0` and the function number takes up all 8 bits of the device and function
+    /// fields.
+    pub fn new_ari(segment: u16, bus: u8, function: u8) -> PciAddress {
+        let mut result = 0;
+        result.set_bits(0..8, function as u32);
+        result.set_bits(8..16, bus as u32);
+        result.set_bits(16..32, segment as u32);
+        PciAddress(result)
+    }
+
+    /// The packed representation of this address, laid out as described above. The layout is stable, and
+    /// includes the segment, so this can be used as a compact key or serialized form of the address.
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+
+    /// Create an address from its packed representation, as returned by [`PciAddress::as_u32`]
+    pub fn from_u32(value: u32) -> PciAddress {
+        PciAddress(value)
+    }
+
     pub fn segment(&self) -> u16 {
         self.0.get_bits(16..32) as u16
     }
@@ -46,13 +109,91 @@ impl PciAddress {
     pub fn function(&self) -> u8 {
         self.0.get_bits(0..3) as u8
     }
+
+    /// The address of function 0 of the same device. Some multifunction devices only implement capabilities that
+    /// apply to the whole device (e.g. ones controlling shared resources) in function 0, so this is where to look
+    /// for them if another function lacks them.
+    ///
+    /// For devices using ARI, the device number is part of the function number, so use
+    /// `PciAddress::new_ari(segment, bus, 0)` instead.
+    pub fn function_zero(&self) -> PciAddress {
+        let mut result = self.0;
+        result.set_bits(0..3, 0);
+        PciAddress(result)
+    }
+
+    /// Returns `true` if both addresses refer to functions of the same device, i.e. they only differ in their
+    /// function number. The segment is compared too, as the same bus and device numbers can be reused on every
+    /// segment.
+    pub fn same_device(&self, other: &PciAddress) -> bool {
+        self.0.get_bits(3..32) == other.0.get_bits(3..32)
+    }
+
+    /// Returns `true` if this address is on one of the buses behind `bridge`, i.e. if its bus falls in the range
+    /// claimed by the bridge's secondary and subordinate bus numbers. These are the functions that would be
+    /// affected by a Secondary Bus Reset of the bridge.
+    ///
+    /// The bridge's own address is on its primary bus, so is not considered to be under it. Functions on a
+    /// different segment, and every function if the bridge hasn't been assigned bus numbers yet, are not under
+    /// it either.
+    pub fn is_under_bus(
+        &self,
+        bridge: &PciPciBridgeHeader,
+        access: &impl ConfigRegionAccess,
+    ) -> bool {
+        if self.segment() != bridge.header().address().segment() {
+            return false;
+        }
+        /*
+         * Bus 0 can never be behind a bridge, so a secondary bus number of 0 means the bridge hasn't been
+         * configured.
+         */
+        bridge
+            .claimed_buses(access)
+            .is_some_and(|buses| *buses.start() != 0 && buses.contains(&self.bus()))
+    }
+
+    /// The number of distinct addresses within a segment, i.e. the size of a table indexed by
+    /// [`PciAddress::linear_index`]
+    pub const ADDRESSES_PER_SEGMENT: usize = 256 * 32 * 8;
+
+    /// A dense index for this address within its segment, in `0..PciAddress::ADDRESSES_PER_SEGMENT`, so
+    /// per-function state can be kept in a flat table. Addresses are ordered by bus, then device, then function.
+    /// The segment is not included.
+    pub fn linear_index(&self) -> usize {
+        self.0.get_bits(0..16) as usize
+    }
+
+    /// The address in `segment` with the given [`PciAddress::linear_index`]. Returns `None` if `index` is out of
+    /// range.
+    pub fn from_linear_index(segment: u16, index: usize) -> Option<PciAddress> {
+        if index >= Self::ADDRESSES_PER_SEGMENT {
+            return None;
+        }
+        let mut result = index as u32;
+        result.set_bits(16..32, segment as u32);
+        Some(PciAddress(result))
+    }
+
+    /// The address of function 0 of device 0 on every bus of `segment`, from bus 0 to bus 255. This is the
+    /// starting point for scanning a segment by brute force, without trusting the bus numbers programmed into its
+    /// bridges.
+    pub fn segment_buses(segment: u16) -> impl Iterator<Item = PciAddress> {
+        (0..=255).map(move |bus| PciAddress::new(segment, bus, 0, 0))
+    }
+
+    /// The function number of this address when the device uses Alternative Routing-ID Interpretation (ARI),
+    /// which combines the device and function fields.
+    pub fn ari_function(&self) -> u8 {
+        self.0.get_bits(0..8) as u8
+    }
 }
 
 impl fmt::Display for PciAddress {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{:02x}-{:02x}:{:02x}.{}",
+            "{:04x}:{:02x}:{:02x}.{}",
             self.segment(),
             self.bus(),
             self.device(),
@@ -61,24 +202,201 @@ impl fmt::Display for PciAddress {
     }
 }
 
+impl fmt::Debug for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 pub type VendorId = u16;
 pub type DeviceId = u16;
 pub type DeviceRevision = u8;
 pub type BaseClass = u8;
 pub type SubClass = u8;
 pub type Interface = u8;
-pub type HeaderType = u8;
 
-// TODO: documentation
+/// Provides access to the configuration space of PCI functions. How the configuration space is reached (e.g.
+/// through the legacy `0xcf8`/`0xcfc` I/O ports or through memory-mapped ECAM regions) is platform-specific, and
+/// so is left to the implementor.
 pub trait ConfigRegionAccess: Send {
+    /// Returns `true` if a function exists at the given address.
     fn function_exists(&self, address: PciAddress) -> bool;
+
+    /// Returns `true` if this access can reach the extended configuration space of PCI Express functions
+    /// (offsets `0x100..0x1000`). The legacy `0xcf8`/`0xcfc` mechanism can only reach the first 256 bytes, so
+    /// this defaults to `false`, and the extended capability list is treated as empty. Implementors using ECAM
+    /// should override it.
+    fn supports_extended(&self) -> bool {
+        false
+    }
+
+    /// Read the dword at `offset` in the configuration space of the function at `address`.
+    ///
+    /// # Safety
+    /// `offset` must be dword-aligned and lie within the configuration space of the function. Reading some
+    /// registers can have side effects on the device.
     unsafe fn read(&self, address: PciAddress, offset: u16) -> u32;
+
+    /// Write `value` to the dword at `offset` in the configuration space of the function at `address`.
+    ///
+    /// # Safety
+    /// `offset` must be dword-aligned and lie within the configuration space of the function. Writing to
+    /// configuration space can change how the device behaves, and the caller must make sure this doesn't break
+    /// any invariants (e.g. by moving a BAR that is in use).
     unsafe fn write(&self, address: PciAddress, offset: u16, value: u32);
+
+    /// Read `buffer.len()` consecutive dwords starting at `offset` into `buffer`. The default implementation calls
+    /// [`ConfigRegionAccess::read`] for each dword, but implementors that can do burst reads should override it.
+    ///
+    /// # Safety
+    /// As for [`ConfigRegionAccess::read`], for every dword in the block.
+    unsafe fn read_block(&self, address: PciAddress, offset: u16, buffer: &mut [u32]) {
+        for (i, dword) in buffer.iter_mut().enumerate() {
+            *dword = self.read(address, offset + i as u16 * 4);
+        }
+    }
+
+    /// Read the byte at `offset`, which doesn't need to be aligned. The default implementation reads the dword
+    /// holding it.
+    ///
+    /// # Safety
+    /// As for [`ConfigRegionAccess::read`], for the dword holding the byte.
+    unsafe fn read_u8(&self, address: PciAddress, offset: u16) -> u8 {
+        let shift = (offset % 4) * 8;
+        (self.read(address, offset & !0x3) >> shift) as u8
+    }
+
+    /// Read the word at `offset`, which must be aligned to 2 bytes. The default implementation reads the dword
+    /// holding it.
+    ///
+    /// # Safety
+    /// As for [`ConfigRegionAccess::read`], for the dword holding the word.
+    unsafe fn read_u16(&self, address: PciAddress, offset: u16) -> u16 {
+        debug_assert!(offset.is_multiple_of(2));
+        let shift = (offset % 4) * 8;
+        (self.read(address, offset & !0x3) >> shift) as u16
+    }
+
+    /// Write the byte at `offset`, which doesn't need to be aligned. The default implementation does a
+    /// read-modify-write of the dword holding it, so the other three bytes are written back with the values they
+    /// were read with. Implementors that can do byte-sized accesses (e.g. through the `0xcfc` I/O port) should
+    /// override it.
+    ///
+    /// # Safety
+    /// As for [`ConfigRegionAccess::write`], for the dword holding the byte. With the default implementation, the
+    /// other bytes of the dword must not hold bits that are cleared by writing ones to them (e.g. the Status
+    /// register), as they would be cleared by writing them back.
+    unsafe fn write_u8(&self, address: PciAddress, offset: u16, value: u8) {
+        let shift = (offset % 4) * 8;
+        let mut dword = self.read(address, offset & !0x3);
+        dword.set_bits(shift as usize..shift as usize + 8, value as u32);
+        self.write(address, offset & !0x3, dword);
+    }
+
+    /// Write the word at `offset`, which must be aligned to 2 bytes. Like [`ConfigRegionAccess::write_u8`], the
+    /// default implementation does a read-modify-write of the dword holding it.
+    ///
+    /// # Safety
+    /// As for [`ConfigRegionAccess::write_u8`].
+    unsafe fn write_u16(&self, address: PciAddress, offset: u16, value: u16) {
+        debug_assert!(offset.is_multiple_of(2));
+        let shift = (offset % 4) * 8;
+        let mut dword = self.read(address, offset & !0x3);
+        dword.set_bits(shift as usize..shift as usize + 16, value as u32);
+        self.write(address, offset & !0x3, dword);
+    }
 }
 
-pub const HEADER_TYPE_ENDPOINT: HeaderType = 0x00;
-pub const HEADER_TYPE_PCI_PCI_BRIDGE: HeaderType = 0x01;
-pub const HEADER_TYPE_CARDBUS_BRIDGE: HeaderType = 0x02;
+/// Panics with a message naming the offending offset if `offset` is not dword-aligned. This is only checked in
+/// builds with debug assertions enabled, and compiles to nothing otherwise.
+///
+/// The access implementations in this crate call this on every access, and implementors of
+/// [`ConfigRegionAccess`] can call it too, as a misaligned offset would otherwise silently access the wrong
+/// register.
+#[inline]
+#[track_caller]
+pub fn debug_assert_dword_aligned(offset: u16) {
+    debug_assert!(
+        offset.is_multiple_of(4),
+        "configuration space offset {:#x} is not dword-aligned",
+        offset
+    );
+}
+
+/// The identity of a function, as read by [`PciHeader::quick_id`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct QuickId {
+    pub vendor: VendorId,
+    pub device: DeviceId,
+    pub class: BaseClass,
+    pub subclass: SubClass,
+    pub interface: Interface,
+    pub revision: DeviceRevision,
+}
+
+/// The layout of the device-dependent region of a function's header, decoded from bits `0..=6` of the Header Type
+/// field.
+///
+/// This used to be an alias for `u8`. Code that matched on the raw value should use [`HeaderType::from`] and
+/// match on the variants instead, with reserved values landing in [`HeaderType::Unknown`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HeaderType {
+    /// Type-0 header, used by endpoints
+    Endpoint,
+    /// Type-1 header, used by PCI-to-PCI bridges
+    PciPciBridge,
+    /// Type-2 header, used by CardBus bridges
+    CardBusBridge,
+    /// A header type reserved by the specification. Functions reporting one of these are likely to be
+    /// misbehaving, and the rest of their header should not be trusted.
+    Unknown(u8),
+}
+
+impl From<u8> for HeaderType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => HeaderType::Endpoint,
+            0x01 => HeaderType::PciPciBridge,
+            0x02 => HeaderType::CardBusBridge,
+            t => HeaderType::Unknown(t),
+        }
+    }
+}
+
+impl HeaderType {
+    /// Decode bits `0..=6` of the Header Type field like [`HeaderType::from`], but return an error carrying the
+    /// raw value for reserved header types instead of [`HeaderType::Unknown`]. `HeaderType` can't implement
+    /// `TryFrom<u8>` as well, as it already has an infallible conversion.
+    pub fn try_from_raw(value: u8) -> Result<HeaderType, UnknownHeaderType> {
+        match HeaderType::from(value) {
+            HeaderType::Unknown(value) => Err(UnknownHeaderType(value)),
+            header_type => Ok(header_type),
+        }
+    }
+}
+
+/*
+ * `HeaderType` used to be a plain `u8`, with these constants for the known values. They are kept so that code
+ * comparing against them still builds.
+ */
+#[deprecated(note = "use `HeaderType::Endpoint` instead")]
+pub const HEADER_TYPE_ENDPOINT: HeaderType = HeaderType::Endpoint;
+#[deprecated(note = "use `HeaderType::PciPciBridge` instead")]
+pub const HEADER_TYPE_PCI_PCI_BRIDGE: HeaderType = HeaderType::PciPciBridge;
+#[deprecated(note = "use `HeaderType::CardBusBridge` instead")]
+pub const HEADER_TYPE_CARDBUS_BRIDGE: HeaderType = HeaderType::CardBusBridge;
+
+/// Error returned when a function reports a header type reserved by the specification. Holds the raw value of bits
+/// `0..=6` of the Header Type field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownHeaderType(pub u8);
+
+/// The header of a function, wrapped in the type matching its layout. See [`PciHeader::typed`].
+pub enum TypedHeader {
+    Endpoint(EndpointHeader),
+    PciPciBridge(PciPciBridgeHeader),
+    CardBusBridge(CardBusBridgeHeader),
+}
 
 /// Every PCI configuration region starts with a header made up of two parts:
 ///    - a predefined region that identify the function (bytes `0x00..0x10`)
@@ -108,6 +426,11 @@ impl PciHeader {
         PciHeader(address)
     }
 
+    /// The address of the function this header belongs to
+    pub fn address(&self) -> PciAddress {
+        self.0
+    }
+
     pub fn id(&self, access: &impl ConfigRegionAccess) -> (VendorId, DeviceId) {
         let id = unsafe { access.read(self.0, 0x00) };
         (
@@ -116,12 +439,45 @@ impl PciHeader {
         )
     }
 
+    /// The Vendor ID and Device ID as a single value, exactly as they are laid out in the first dword of the
+    /// header: `(device_id as u32) << 16 | vendor_id as u32`. This is handy as a key for tables of known devices.
+    /// It reads as `0xffffffff` if no function is present.
+    pub fn packed_id(&self, access: &impl ConfigRegionAccess) -> u32 {
+        unsafe { access.read(self.0, 0x00) }
+    }
+
     pub fn header_type(&self, access: &impl ConfigRegionAccess) -> HeaderType {
         /*
          * Read bits 0..=6 of the Header Type. Bit 7 dictates whether the device has multiple functions and so
          * isn't returned here.
          */
-        unsafe { access.read(self.0, 0x0c) }.get_bits(16..23) as HeaderType
+        HeaderType::from(unsafe { access.read(self.0, 0x0c) }.get_bits(16..23) as u8)
+    }
+
+    /// Read the Header Type field and wrap the header in the type matching its layout. This reads the header
+    /// type once, rather than once for each `from_header` tried. Returns [`UnknownHeaderType`] if the header type
+    /// is reserved, in which case the rest of the header shouldn't be trusted.
+    pub fn typed(
+        &self,
+        access: &impl ConfigRegionAccess,
+    ) -> Result<TypedHeader, UnknownHeaderType> {
+        let raw = unsafe { access.read(self.0, 0x0c) }.get_bits(16..23) as u8;
+        Ok(match HeaderType::try_from_raw(raw)? {
+            HeaderType::Endpoint => TypedHeader::Endpoint(EndpointHeader(self.0)),
+            HeaderType::PciPciBridge => TypedHeader::PciPciBridge(PciPciBridgeHeader(self.0)),
+            HeaderType::CardBusBridge => TypedHeader::CardBusBridge(CardBusBridgeHeader(self.0)),
+            HeaderType::Unknown(value) => return Err(UnknownHeaderType(value)),
+        })
+    }
+
+    /// Read the Cache Line Size register
+    pub fn cache_line_size(&self, access: &impl ConfigRegionAccess) -> CacheLineSize {
+        CacheLineSize::from_raw(unsafe { access.read_u8(self.0, 0x0c) })
+    }
+
+    /// Write the Cache Line Size register, leaving the rest of its dword unchanged
+    pub fn set_cache_line_size(&self, access: &impl ConfigRegionAccess, size: CacheLineSize) {
+        unsafe { access.write_u8(self.0, 0x0c, size.as_dwords()) }
     }
 
     pub fn has_multiple_functions(&self, access: &impl ConfigRegionAccess) -> bool {
@@ -131,6 +487,21 @@ impl PciHeader {
         unsafe { access.read(self.0, 0x0c) }.get_bit(23)
     }
 
+    /// Read the identity of the function with just the two dwords that hold it: the Vendor and Device IDs, and
+    /// the revision and class code. This is all that is usually needed to decide which driver to bind.
+    pub fn quick_id(&self, access: &impl ConfigRegionAccess) -> QuickId {
+        let id = unsafe { access.read(self.0, 0x00) };
+        let class = unsafe { access.read(self.0, 0x08) };
+        QuickId {
+            vendor: id.get_bits(0..16) as VendorId,
+            device: id.get_bits(16..32) as DeviceId,
+            class: class.get_bits(24..32) as BaseClass,
+            subclass: class.get_bits(16..24) as SubClass,
+            interface: class.get_bits(8..16) as Interface,
+            revision: class.get_bits(0..8) as DeviceRevision,
+        }
+    }
+
     pub fn revision_and_class(
         &self,
         access: &impl ConfigRegionAccess,
@@ -148,8 +519,110 @@ impl PciHeader {
         let data = unsafe { access.read(self.0, 0x4).get_bits(16..32) };
         StatusRegister::new(data as u16)
     }
+
+    pub fn command(&self, access: &impl ConfigRegionAccess) -> CommandRegister {
+        let data = unsafe { access.read(self.0, 0x4).get_bits(0..16) };
+        CommandRegister::new(data as u16)
+    }
+
+    /// Write the Command register. The Status register shares the same dword, so its RW1C bits are written as
+    /// zero to leave them untouched.
+    pub fn set_command(&self, access: &impl ConfigRegionAccess, command: CommandRegister) {
+        unsafe { access.write(self.0, 0x4, command.raw() as u32) };
+    }
+
+    /// Read the Status register and clear the RW1C bits set in `bits` (e.g. a detected parity error) with a
+    /// single write, preserving the Command register. Returns the Status register as it was before clearing.
+    pub fn read_and_clear_status(
+        &self,
+        access: &impl ConfigRegionAccess,
+        bits: u16,
+    ) -> StatusRegister {
+        let dword = unsafe { access.read(self.0, 0x4) };
+        let status = StatusRegister::new(dword.get_bits(16..32) as u16);
+        unsafe {
+            access.write(
+                self.0,
+                0x4,
+                StatusRegister::clear_dword(dword.get_bits(0..16) as u16, bits),
+            )
+        };
+        status
+    }
+}
+
+/// Read the Header Type field of the function at `address` with a single byte read of offset `0x0e`, returning
+/// the layout of the rest of the header and whether the device has multiple functions. This is the same
+/// information as [`PciHeader::header_type`] and [`PciHeader::has_multiple_functions`] give, at half the number of
+/// reads, which matters when scanning buses through slow access mechanisms.
+pub fn read_header_type(
+    access: &impl ConfigRegionAccess,
+    address: PciAddress,
+) -> (HeaderType, bool) {
+    let field = unsafe { access.read_u8(address, 0x0e) };
+    (HeaderType::from(field.get_bits(0..7)), field.get_bit(7))
+}
+
+/// Error returned when a bounded polling loop gives up before the condition it was waiting for became true
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+/// Repeatedly read the Status register of the function at `address` until `predicate` returns `true`, and
+/// return the value that satisfied it. Gives up with [`Timeout`] after `max_iterations` reads, so this can't hang
+/// on a device that never settles.
+///
+/// There is no delay between reads, as the crate has no notion of time. Callers that need to wait for a given
+/// duration should pick `max_iterations` based on how long a configuration read takes on their platform.
+pub fn poll_until<F>(
+    access: &impl ConfigRegionAccess,
+    address: PciAddress,
+    predicate: F,
+    max_iterations: usize,
+) -> Result<StatusRegister, Timeout>
+where
+    F: Fn(&StatusRegister) -> bool,
+{
+    let header = PciHeader::new(address);
+    (0..max_iterations)
+        .map(|_| header.status(access))
+        .find(|status| predicate(status))
+        .ok_or(Timeout)
 }
 
+/// The fields of a Type-0 (endpoint) header, as `(name, offset, width)` with the width in bytes, in order of
+/// offset. This is the layout drawn out in the documentation of [`EndpointHeader`], in a form that can be checked
+/// against, e.g. to test that code reading the header uses the right offsets.
+pub const ENDPOINT_HEADER_LAYOUT: [(&str, u16, u8); 25] = [
+    ("Vendor ID", 0x00, 2),
+    ("Device ID", 0x02, 2),
+    ("Command", 0x04, 2),
+    ("Status", 0x06, 2),
+    ("Revision ID", 0x08, 1),
+    ("Class Code", 0x09, 3),
+    ("Cache Line Size", 0x0c, 1),
+    ("Latency Timer", 0x0d, 1),
+    ("Header Type", 0x0e, 1),
+    ("BIST", 0x0f, 1),
+    ("Base Address Register 0", 0x10, 4),
+    ("Base Address Register 1", 0x14, 4),
+    ("Base Address Register 2", 0x18, 4),
+    ("Base Address Register 3", 0x1c, 4),
+    ("Base Address Register 4", 0x20, 4),
+    ("Base Address Register 5", 0x24, 4),
+    ("CardBus CIS Pointer", 0x28, 4),
+    ("Subsystem Vendor ID", 0x2c, 2),
+    ("Subsystem ID", 0x2e, 2),
+    ("Expansion ROM Base Address", 0x30, 4),
+    ("Capabilities Pointer", 0x34, 1),
+    /*
+     * Bytes 0x35 to 0x3b are reserved.
+     */
+    ("Interrupt Line", 0x3c, 1),
+    ("Interrupt Pin", 0x3d, 1),
+    ("Min_Gnt", 0x3e, 1),
+    ("Max_Lat", 0x3f, 1),
+];
+
 /// Endpoints have a Type-0 header, so the remainder of the header is of the form:
 /// ```ignore
 ///     32                           16                              0
@@ -204,7 +677,7 @@ impl EndpointHeader {
         access: &impl ConfigRegionAccess,
     ) -> Option<EndpointHeader> {
         match header.header_type(access) {
-            0x00 => Some(EndpointHeader(header.0)),
+            HeaderType::Endpoint => Some(EndpointHeader(header.0)),
             _ => None,
         }
     }
@@ -214,6 +687,8 @@ impl EndpointHeader {
         StatusRegister::new(data as u16)
     }
 
+    /// The generic view of this function's header. This doesn't access the hardware, so it is cheap to call
+    /// whenever the generic view is needed again.
     pub fn header(&self) -> PciHeader {
         PciHeader(self.0)
     }
@@ -221,12 +696,15 @@ impl EndpointHeader {
     pub fn capability_pointer(&self, access: &impl ConfigRegionAccess) -> u16 {
         let status = self.status(access);
         if status.has_capability_list() {
-            unsafe { access.read(self.0, 0x34).get_bits(0..8) as u16 }
+            unsafe { access.read(self.0, 0x34).get_bits(0..8) as u16 & 0xfc }
         } else {
             0
         }
     }
 
+    /// Iterate over the standard capabilities of this function, which live in the first 256 bytes of its
+    /// configuration space. Extended capabilities use a different list format and ID space, so they are walked
+    /// separately by [`EndpointHeader::extended_capabilities`].
     pub fn capabilities<'a, T: ConfigRegionAccess>(
         &self,
         access: &'a T,
@@ -235,60 +713,175 @@ impl EndpointHeader {
         CapabilityIterator::new(self.0, pointer, access)
     }
 
-    /// Get the contents of a BAR in a given slot. Empty bars will return `None`.
+    /// Iterate over the byte ranges of configuration space taken up by this function's capabilities, e.g. to check
+    /// that a mapping of the configuration space covers all of them. See [`PciCapability::byte_range`].
+    pub fn capability_ranges<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> impl Iterator<Item = (CapabilityId, Range<usize>)> + 'a {
+        self.capabilities(access)
+            .map(move |capability| (capability.id(), capability.byte_range(access)))
+    }
+
+    /// Iterate over the byte ranges of configuration space taken up by this function's extended capabilities
+    pub fn extended_capability_ranges<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> impl Iterator<Item = (ExtendedCapabilityId, Range<usize>)> + 'a {
+        self.extended_capabilities(access)
+            .map(|capability| (capability.id(), capability.byte_range()))
+    }
+
+    /// Parse the capability at a known offset, e.g. one remembered from an earlier walk of the capability list.
+    /// Returns `None` if a Null capability is found there.
+    pub fn capability_at(
+        &self,
+        offset: CapabilityOffset,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<PciCapability> {
+        PciCapability::read_at(self.0, offset, access)
+    }
+
+    /// Iterate over the capability list starting from the capability at `start`, rather than from the
+    /// Capabilities Pointer. This can be used to resume a walk from an offset remembered from an earlier one. The
+    /// capability at `start` is included, and the list is still bounded in case it links back on itself.
+    pub fn capabilities_from<'a, T: ConfigRegionAccess>(
+        &self,
+        start: CapabilityOffset,
+        access: &'a T,
+    ) -> CapabilityIterator<'a, T> {
+        CapabilityIterator::new(self.0, start.get() as u16, access)
+    }
+
+    /// Iterate over the PCI Express extended capabilities of this function, which live in the extended
+    /// configuration space starting at offset `0x100`.
+    pub fn extended_capabilities<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> ExtendedCapabilityIterator<'a, T> {
+        ExtendedCapabilityIterator::new(self.0, 0x100, access)
+    }
+
+    /// Check whether the BAR in a given slot is implemented, by writing all ones to it and checking whether any
+    /// bits stuck. The original value is restored afterwards.
+    ///
+    /// ### Note
+    /// Like [`EndpointHeader::bar`], this writes to the BAR, so it should not be called while the function is
+    /// decoding accesses. The slot holding the upper half of a 64-bit BAR will be reported as implemented.
+    pub fn bar_implemented(&self, slot: BarIndex, access: &impl ConfigRegionAccess) -> bool {
+        unsafe { self.probe_bar_dword(slot.offset(), access) != 0 }
+    }
+
+    /// Write all ones to the BAR dword at `offset` and read back which bits stuck, restoring the original value
+    /// afterwards. Bits that read back as zero are hardwired, which is how the size of a BAR is found.
+    ///
+    /// # Safety
+    /// The function must not be decoding accesses through the BAR while it is probed.
+    unsafe fn probe_bar_dword(&self, offset: u16, access: &impl ConfigRegionAccess) -> u32 {
+        let original = access.read(self.0, offset);
+        access.write(self.0, offset, 0xffffffff);
+        let readback = access.read(self.0, offset);
+        access.write(self.0, offset, original);
+        readback
+    }
+
+    /// Read the address a BAR is currently programmed with, without probing its size. For 64-bit BARs, `slot`
+    /// must be the lower of the two slots, and both halves are combined. The address of an I/O BAR is in I/O
+    /// space.
+    ///
+    /// Unlike [`EndpointHeader::bar`], this only reads the BAR, so it is safe to use on functions that are
+    /// decoding accesses. As a result it can't tell unimplemented BARs from ones that haven't been assigned an
+    /// address: both return `None`, as does a 64-bit BAR in the last slot.
+    pub fn bar_base(&self, slot: BarIndex, access: &impl ConfigRegionAccess) -> Option<u64> {
+        let bar = unsafe { access.read(self.0, slot.offset()) };
+        let base = if bar.get_bit(0) {
+            (bar & !0x3) as u64
+        } else {
+            match bar.get_bits(1..3) {
+                0b00 => (bar & !0xf) as u64,
+                0b10 => {
+                    let high = BarIndex::new(slot.get() + 1)?;
+                    combine_bar64(bar, unsafe { access.read(self.0, high.offset()) })
+                }
+                _ => return None,
+            }
+        };
+        match base {
+            0 => None,
+            base => Some(base),
+        }
+    }
+
+    /// Get the contents of a BAR in a given slot. Empty bars, and memory BARs of a reserved type, will return
+    /// `None`.
     ///
     /// ### Note
     /// 64-bit memory BARs use two slots, so if one is decoded in e.g. slot #0, this method should not be called
     /// for slot #1
-    pub fn bar(&self, slot: u8, access: &impl ConfigRegionAccess) -> Option<Bar> {
-        let offset = 0x10 + (slot as u16) * 4;
+    pub fn bar(&self, slot: BarIndex, access: &impl ConfigRegionAccess) -> Option<Bar> {
+        let offset = slot.offset();
         let bar = unsafe { access.read(self.0, offset) };
 
         /*
          * If bit 0 is `0`, the BAR is in memory. If it's `1`, it's in I/O.
          */
-        if bar.get_bit(0) == false {
+        if !bar.get_bit(0) {
             let prefetchable = bar.get_bit(3);
             let address = bar.get_bits(4..32) << 4;
 
-            // TODO: if the bar is 64-bits, do we need to do this on both BARs?
-            let size = unsafe {
-                access.write(self.0, offset, 0xffffffff);
-                let mut readback = access.read(self.0, offset);
-                access.write(self.0, offset, address);
+            match bar.get_bits(1..3) {
+                0b00 => {
+                    let mut readback = unsafe { self.probe_bar_dword(offset, access) };
+                    /*
+                     * If the entire readback value is zero, the BAR is not implemented, so we return `None`.
+                     */
+                    if readback == 0x0 {
+                        return None;
+                    }
+                    readback.set_bits(0..4, 0);
+                    let size = bar_size(0xffffffff_00000000 | readback as u64)?;
 
-                /*
-                 * If the entire readback value is zero, the BAR is not implemented, so we return `None`.
-                 */
-                if readback == 0x0 {
-                    return None;
+                    Some(Bar::Memory32 {
+                        address,
+                        /*
+                         * A 32-bit BAR must have at least one writable address bit, so its size is always
+                         * below 4GiB.
+                         */
+                        size: u32::try_from(size).ok()?,
+                        prefetchable,
+                    })
                 }
-
-                readback.set_bits(0..4, 0);
-                1 << readback.trailing_zeros()
-            };
-
-            match bar.get_bits(1..3) {
-                0b00 => Some(Bar::Memory32 {
-                    address,
-                    size,
-                    prefetchable,
-                }),
                 0b10 => {
-                    let address = {
-                        let mut address = address as u64;
-                        // TODO: do we need to mask off the lower bits on this?
-                        address.set_bits(32..64, unsafe { access.read(self.0, offset + 4) } as u64);
-                        address
+                    /*
+                     * The size of a 64-bit BAR has to be probed across both slots, as BARs of 4GiB or larger have
+                     * no writable address bits in the lower slot. A 64-bit BAR can't start in the last slot, as
+                     * the dword after it isn't a BAR.
+                     */
+                    let high = BarIndex::new(slot.get() + 1)?.offset();
+                    let address_high = unsafe { access.read(self.0, high) };
+                    let (mut readback_low, readback_high) = unsafe {
+                        (
+                            self.probe_bar_dword(offset, access),
+                            self.probe_bar_dword(high, access),
+                        )
                     };
+                    readback_low.set_bits(0..4, 0);
+                    let mut mask = readback_low as u64;
+                    mask.set_bits(32..64, readback_high as u64);
+                    let size = bar_size(mask)?;
+
                     Some(Bar::Memory64 {
-                        address,
-                        size: size as u64,
+                        address: combine_bar64(address, address_high),
+                        size,
                         prefetchable,
                     })
                 }
-                // TODO: should we bother to return an error here?
-                _ => panic!("BAR Memory type is reserved!"),
+                /*
+                 * Type `0b01` was used by legacy BARs that had to be placed below 1MiB, and `0b11` is reserved.
+                 * Neither can be decoded, so the BAR is treated as unimplemented rather than trusting the rest of
+                 * it.
+                 */
+                _ => None,
             }
         } else {
             Some(Bar::Io {
@@ -296,23 +889,1754 @@ impl EndpointHeader {
             })
         }
     }
-}
 
-pub const MAX_BARS: usize = 6;
+    /// Probe the size of the I/O BAR in `slot`, in bytes. Returns `None` if no address bits are writable.
+    fn io_bar_size(&self, slot: BarIndex, access: &impl ConfigRegionAccess) -> Option<u64> {
+        let mut readback = unsafe { self.probe_bar_dword(slot.offset(), access) };
+        readback.set_bits(0..2, 0);
+        /*
+         * Devices that only decode 16 bits of I/O addresses can hardwire the upper half of the BAR to zero, so it
+         * is ignored when working out the size.
+         */
+        readback.set_bits(16..32, 0xffff);
+        bar_size(0xffffffff_00000000 | readback as u64)
+    }
 
-#[derive(Clone, Copy, Debug)]
-pub enum Bar {
-    Memory32 {
-        address: u32,
-        size: u32,
-        prefetchable: bool,
-    },
-    Memory64 {
-        address: u64,
-        size: u64,
-        prefetchable: bool,
-    },
-    Io {
-        port: u32,
-    },
+    /// Probe every implemented BAR of this function, in slot order. The slot holding the upper half of a 64-bit
+    /// BAR is skipped.
+    fn bars<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> impl Iterator<Item = (BarIndex, Bar)> + 'a {
+        let header = EndpointHeader(self.0);
+        let mut slot = 0;
+        core::iter::from_fn(move || {
+            while slot < MAX_BARS as u8 {
+                let index = BarIndex(slot);
+                let bar = header.bar(index, access);
+                slot += if let Some(Bar::Memory64 { .. }) = bar {
+                    2
+                } else {
+                    1
+                };
+                if let Some(bar) = bar {
+                    return Some((index, bar));
+                }
+            }
+            None
+        })
+    }
+
+    /// Iterate over the resources decoded by this function, whether they are described by its BARs or by an
+    /// Enhanced Allocation capability. Resources are yielded in order of the BAR slot they occupy (or stand in
+    /// for, in the case of Enhanced Allocation). Where both describe the same slot, the BAR takes precedence.
+    ///
+    /// ### Note
+    /// Like [`EndpointHeader::bar`], this writes to the BARs to determine their sizes, so it should not be called
+    /// while the function is decoding accesses.
+    pub fn resources(&self, access: &impl ConfigRegionAccess) -> impl Iterator<Item = Resource> {
+        let mut resources: [Option<Resource>; MAX_BARS] = [None; MAX_BARS];
+
+        /*
+         * Slots holding the upper half of a 64-bit BAR are occupied too, so Enhanced Allocation entries naming them
+         * aren't treated as separate resources.
+         */
+        let mut occupied = [false; MAX_BARS];
+        for (slot, bar) in self.bars(access) {
+            occupied[slot.get() as usize] = true;
+            if let Bar::Memory64 { .. } = bar {
+                occupied[slot.get() as usize + 1] = true;
+            }
+            let (base, size, kind) = match bar {
+                Bar::Memory32 {
+                    address,
+                    size,
+                    prefetchable,
+                } => (
+                    address as u64,
+                    size as u64,
+                    ResourceKind::Memory { prefetchable },
+                ),
+                Bar::Memory64 {
+                    address,
+                    size,
+                    prefetchable,
+                } => (address, size, ResourceKind::Memory { prefetchable }),
+                Bar::Io { port } => match self.io_bar_size(slot, access) {
+                    Some(size) => ((port as u64) << 2, size, ResourceKind::Io),
+                    None => continue,
+                },
+            };
+            resources[slot.get() as usize] = Some(Resource {
+                index: slot.get(),
+                base,
+                size,
+                kind,
+            });
+        }
+
+        let ea = self
+            .capabilities(access)
+            .find_map(|capability| match capability {
+                PciCapability::EnhancedAllocation(ea) => Some(ea),
+                _ => None,
+            });
+        for entry in ea.iter().flat_map(|ea| ea.entries(access)) {
+            let slot = match entry.bar_equivalent {
+                BarEquivalent::Bar(slot) if entry.enabled => slot as usize,
+                _ => continue,
+            };
+            let kind = match entry.property() {
+                EaProperty::MemoryNonPrefetchable => ResourceKind::Memory {
+                    prefetchable: false,
+                },
+                EaProperty::MemoryPrefetchable => ResourceKind::Memory { prefetchable: true },
+                EaProperty::Io => ResourceKind::Io,
+                _ => continue,
+            };
+            if !occupied[slot] {
+                resources[slot] = Some(Resource {
+                    index: slot as u8,
+                    base: entry.base,
+                    size: entry.size(),
+                    kind,
+                });
+            }
+        }
+
+        IntoIterator::into_iter(resources).flatten()
+    }
+
+    /// Probe every BAR of this function and collect the memory regions they decode, along with their combined
+    /// size. This is the information needed to plan where the function's BARs can be placed in the physical
+    /// address space.
+    ///
+    /// ### Note
+    /// I/O BARs don't take up memory space, so they are not included. Like [`EndpointHeader::bar`], this writes
+    /// to the BARs to determine their sizes, so it should not be called while the function is decoding memory
+    /// accesses.
+    pub fn total_bar_footprint(&self, access: &impl ConfigRegionAccess) -> BarFootprint {
+        let mut footprint = BarFootprint {
+            entries: [None; MAX_BARS],
+            total: 0,
+        };
+
+        for (slot, bar) in self.bars(access) {
+            let (size, space, prefetchable) = match bar {
+                Bar::Memory32 {
+                    size, prefetchable, ..
+                } => (size as u64, BarSpace::Memory32, prefetchable),
+                Bar::Memory64 {
+                    size, prefetchable, ..
+                } => (size, BarSpace::Memory64, prefetchable),
+                Bar::Io { .. } => continue,
+            };
+
+            footprint.entries[slot.get() as usize] = Some(BarFootprintEntry {
+                index: slot.get(),
+                size,
+                /*
+                 * BARs are always naturally aligned to their size.
+                 */
+                alignment: size,
+                space,
+                prefetchable,
+            });
+            footprint.total += size;
+        }
+
+        footprint
+    }
+
+    /// The strictest alignment required by any of this function's memory BARs, in bytes, or `0` if it has none.
+    /// A window holding all of the function's BARs should start at an address aligned to this, so that each BAR
+    /// can be placed inside it at its own natural alignment. See [`EndpointHeader::total_bar_footprint`] for how
+    /// the BARs are probed.
+    pub fn max_bar_alignment(&self, access: &impl ConfigRegionAccess) -> u64 {
+        self.total_bar_footprint(access).max_alignment()
+    }
+
+    /// Get the Subsystem Vendor ID and Subsystem ID of this function, which identify the board or system it is
+    /// part of
+    pub fn subsystem(&self, access: &impl ConfigRegionAccess) -> (VendorId, DeviceId) {
+        let data = unsafe { access.read(self.0, 0x2c) };
+        (
+            data.get_bits(0..16) as VendorId,
+            data.get_bits(16..32) as DeviceId,
+        )
+    }
+
+    /// Get the Expansion ROM BAR of this function. Returns `None` if the function doesn't have an option ROM.
+    ///
+    /// ### Note
+    /// Like [`EndpointHeader::bar`], this writes to the BAR to determine the size of the ROM.
+    pub fn expansion_rom(&self, access: &impl ConfigRegionAccess) -> Option<ExpansionRom> {
+        ExpansionRom::probe(self.0, 0x30, access)
+    }
+
+    /// The legacy interrupt pin used by this function. Returns `None` if the function doesn't use one.
+    pub fn interrupt_pin(&self, access: &impl ConfigRegionAccess) -> Option<InterruptPin> {
+        InterruptPin::try_from(unsafe { access.read_u8(self.0, 0x3d) }).ok()
+    }
+
+    /// The Interrupt Line register, which is written by firmware or the OS to record which interrupt controller
+    /// input the function's INTx pin is routed to. It has no effect on the function itself.
+    pub fn interrupt_line(&self, access: &impl ConfigRegionAccess) -> u8 {
+        unsafe { access.read_u8(self.0, 0x3c) }
+    }
+
+    /// Write the Interrupt Line register. Only this byte is changed; the Interrupt Pin, Min_Gnt and Max_Lat
+    /// registers sharing its dword are left as they are.
+    pub fn set_interrupt_line(&self, access: &impl ConfigRegionAccess, line: u8) {
+        unsafe { access.write_u8(self.0, 0x3c, line) }
+    }
+
+    /// Will be `true` if any of this function's memory BARs is 64-bit, and so can be placed above 4GiB. I/O BARs
+    /// are skipped. Only the type bits of the BARs are read, so unlike [`EndpointHeader::bar`] this doesn't write
+    /// to them.
+    pub fn supports_64bit_bars(&self, access: &impl ConfigRegionAccess) -> bool {
+        let mut bars = [0u32; MAX_BARS];
+        unsafe { access.read_block(self.0, 0x10, &mut bars) };
+        /*
+         * The upper half of a 64-bit BAR is never looked at, as the scan stops at its lower half.
+         */
+        bars.iter()
+            .any(|bar| !bar.get_bit(0) && bar.get_bits(1..3) == 0b10)
+    }
+
+    /// Apply `quirk` to this function if it matches the function's Vendor and Device IDs. Returns `true` if the
+    /// quirk was applied.
+    pub fn apply_quirk<A: ConfigRegionAccess, Q: Quirk>(&self, access: &A, quirk: &Q) -> bool {
+        let (vendor, device) = self.header().id(access);
+        if !quirk.matches(vendor, device) {
+            return false;
+        }
+        quirk.apply(self, access);
+        true
+    }
+
+    /// Find all of the error reporting mechanisms of this function, so they can be cleared and configured
+    /// together
+    pub fn error_reporting(&self, access: &impl ConfigRegionAccess) -> ErrorReporting {
+        ErrorReporting::new(self, access)
+    }
+
+    /// Find the best way of resetting this function. Like Linux, this prefers the PCI Express FLR, then the
+    /// Advanced Features FLR, then a D3hot to D0 transition, and finally falls back to a Secondary Bus Reset.
+    /// Power management is only picked if the function doesn't advertise No_Soft_Reset, as such functions keep
+    /// their state through the transition.
+    pub fn preferred_reset(&self, access: &impl ConfigRegionAccess) -> ResetMethod {
+        ResetMethod::find(self, access)
+    }
+
+    /// Reset this function with the method chosen by [`EndpointHeader::preferred_reset`], and return it. The
+    /// crate has no notion of time, so `wait_ms` is called with a number of milliseconds whenever the reset needs
+    /// the caller to wait.
+    ///
+    /// An FLR is only initiated once the function has no transactions pending, and is followed by a 100ms wait so
+    /// that the function can be accessed as soon as this returns.
+    ///
+    /// ### Note
+    /// Whichever method is used, the function loses its configuration, which can be saved beforehand with
+    /// [`EndpointHeader::save_state`].
+    pub fn reset(
+        &self,
+        access: &impl ConfigRegionAccess,
+        wait_ms: impl FnMut(u32),
+    ) -> Result<ResetMethod, ResetError> {
+        let method = self.preferred_reset(access);
+        method.perform(access, wait_ms)?;
+        Ok(method)
+    }
+
+    /// Save the writable configuration of this function, so it can be put back with
+    /// [`EndpointHeader::restore_state`] after a reset (e.g. a Function Level Reset or Secondary Bus Reset) wipes
+    /// it. See [`ConfigSnapshot`] for what is saved.
+    pub fn save_state(&self, access: &impl ConfigRegionAccess) -> ConfigSnapshot {
+        ConfigSnapshot::capture(self.0, access, self.capabilities(access))
+    }
+
+    /// Write a configuration saved by [`EndpointHeader::save_state`] back to this function. Read-only fields are
+    /// not written. Decoding is disabled while the BARs are restored, and the Command register is restored last.
+    pub fn restore_state(&self, access: &impl ConfigRegionAccess, snapshot: &ConfigSnapshot) {
+        snapshot.restore(self.0, access)
+    }
+
+    /// Write a multi-line, human-readable description of this function to `out`, in the spirit of `lspci -vvv`.
+    /// This covers the IDs, class, Command and Status registers, BARs, and every capability and extended
+    /// capability found.
+    ///
+    /// This only reads configuration space, so it is safe to call on a function that is in use. As a result, the
+    /// BARs are shown with the addresses they are programmed with, but not their sizes, which can only be found
+    /// by probing them with [`EndpointHeader::bar`].
+    pub fn dump(&self, access: &impl ConfigRegionAccess, out: &mut impl fmt::Write) -> fmt::Result {
+        let header = self.header();
+        let (vendor, device) = header.id(access);
+        let (revision, base, sub, interface) = header.revision_and_class(access);
+        writeln!(
+            out,
+            "{} {:04x}:{:04x} (rev {:02x})",
+            self.0, vendor, device, revision
+        )?;
+        writeln!(
+            out,
+            "    Class: {:02x}{:02x}, prog-if {:02x} ({:?})",
+            base,
+            sub,
+            interface,
+            DeviceType::from((base, sub))
+        )?;
+        writeln!(out, "    Command: {:?}", header.command(access))?;
+        writeln!(out, "    Status: {:?}", header.status(access))?;
+
+        let mut bars = [0u32; MAX_BARS];
+        unsafe { access.read_block(self.0, 0x10, &mut bars) };
+        let mut slot = 0;
+        while slot < MAX_BARS {
+            let bar = bars[slot];
+            let base = self.bar_base(BarIndex(slot as u8), access);
+            match (bar.get_bit(0), bar.get_bits(1..3)) {
+                _ if bar == 0 => (),
+                (true, _) => writeln!(
+                    out,
+                    "    BAR{}: I/O ports at {:#x}",
+                    slot,
+                    base.unwrap_or(0)
+                )?,
+                (false, 0b00) | (false, 0b10) => writeln!(
+                    out,
+                    "    BAR{}: Memory at {:#x} ({}-bit, {})",
+                    slot,
+                    base.unwrap_or(0),
+                    if bar.get_bits(1..3) == 0b10 { 64 } else { 32 },
+                    if bar.get_bit(3) {
+                        "prefetchable"
+                    } else {
+                        "non-prefetchable"
+                    }
+                )?,
+                (false, _) => {
+                    writeln!(out, "    BAR{}: Reserved memory type ({:#010x})", slot, bar)?
+                }
+            }
+            /*
+             * The slot after a 64-bit BAR holds the upper half of its address.
+             */
+            slot += if !bar.get_bit(0) && bar.get_bits(1..3) == 0b10 {
+                2
+            } else {
+                1
+            };
+        }
+
+        for capability in self.capabilities(access) {
+            writeln!(out, "    Capability: {:?}", capability)?;
+        }
+        for capability in self.extended_capabilities(access) {
+            writeln!(
+                out,
+                "    Extended capability at {:#05x}: {} (version {})",
+                capability.address().offset,
+                capability.id(),
+                capability.version()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl From<EndpointHeader> for PciHeader {
+    fn from(header: EndpointHeader) -> Self {
+        header.header()
+    }
+}
+
+/// PCI-to-PCI bridges have a Type-1 header, so the remainder of the header is of the form:
+/// ```ignore
+///     32                           16                              0
+///     +-----------------------------------------------------------+ 0x00
+///     |                                                           |
+///     |                Predefined region of header                |
+///     |                                                           |
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |                  Base Address Register 0                  | 0x10
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |                  Base Address Register 1                  | 0x14
+///     |                                                           |
+///     +--------------+--------------+--------------+--------------+
+///     | Secondary    | Subordinate  |  Secondary   | Primary Bus  | 0x18
+///     |Latency Timer | Bus Number   |  Bus Number  |   Number     |
+///     +--------------+--------------+--------------+--------------+
+///     |      Secondary Status       |  I/O Limit   |   I/O Base   | 0x1C
+///     |                             |              |              |
+///     +-----------------------------+--------------+--------------+
+///     |        Memory Limit         |         Memory Base         | 0x20
+///     |                             |                             |
+///     +-----------------------------+-----------------------------+
+///     |  Prefetchable Memory Limit  |  Prefetchable Memory Base   | 0x24
+///     |                             |                             |
+///     +-----------------------------+-----------------------------+
+///     |             Prefetchable Base Upper 32 Bits               | 0x28
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |             Prefetchable Limit Upper 32 Bits              | 0x2C
+///     |                                                           |
+///     +-----------------------------+-----------------------------+
+///     |   I/O Limit Upper 16 Bits   |   I/O Base Upper 16 Bits    | 0x30
+///     |                             |                             |
+///     +-----------------------------+--------------+--------------+
+///     |              Reserved                      |  Capability  | 0x34
+///     |                                            |   Pointer    |
+///     +--------------------------------------------+--------------+
+///     |                  Expansion ROM base address               | 0x38
+///     |                                                           |
+///     +-----------------------------+--------------+--------------+
+///     |    Bridge Control           |  Interrupt   | Interrupt    | 0x3C
+///     |                             |     PIN      |   Line       |
+///     +-----------------------------+--------------+--------------+
+/// ```
+pub struct PciPciBridgeHeader(PciAddress);
+
+impl PciPciBridgeHeader {
+    pub fn from_header(
+        header: PciHeader,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<PciPciBridgeHeader> {
+        match header.header_type(access) {
+            HeaderType::PciPciBridge => Some(PciPciBridgeHeader(header.0)),
+            _ => None,
+        }
+    }
+
+    /// The generic view of this function's header. This doesn't access the hardware, so it is cheap to call
+    /// whenever the generic view is needed again.
+    pub fn header(&self) -> PciHeader {
+        PciHeader(self.0)
+    }
+
+    pub fn status(&self, access: &impl ConfigRegionAccess) -> StatusRegister {
+        let data = unsafe { access.read(self.0, 0x4).get_bits(16..32) };
+        StatusRegister::new(data as u16)
+    }
+
+    pub fn capability_pointer(&self, access: &impl ConfigRegionAccess) -> u16 {
+        let status = self.status(access);
+        if status.has_capability_list() {
+            unsafe { access.read(self.0, 0x34).get_bits(0..8) as u16 & 0xfc }
+        } else {
+            0
+        }
+    }
+
+    /// Iterate over the standard capabilities of this bridge. Its extended capabilities are walked separately by
+    /// [`PciPciBridgeHeader::extended_capabilities`].
+    pub fn capabilities<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> CapabilityIterator<'a, T> {
+        let pointer = self.capability_pointer(access);
+        CapabilityIterator::new(self.0, pointer, access)
+    }
+
+    /// Iterate over the PCI Express extended capabilities of this bridge, which live in the extended configuration
+    /// space starting at offset `0x100`. The list is empty for conventional PCI bridges.
+    pub fn extended_capabilities<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> ExtendedCapabilityIterator<'a, T> {
+        ExtendedCapabilityIterator::new(self.0, 0x100, access)
+    }
+
+    /// The number of the bus the bridge is connected to on its upstream side
+    pub fn primary_bus_number(&self, access: &impl ConfigRegionAccess) -> u8 {
+        unsafe { access.read(self.0, 0x18) }.get_bits(0..8) as u8
+    }
+
+    /// The number of the bus directly behind the bridge
+    pub fn secondary_bus_number(&self, access: &impl ConfigRegionAccess) -> u8 {
+        unsafe { access.read(self.0, 0x18) }.get_bits(8..16) as u8
+    }
+
+    /// The highest bus number behind the bridge, including buses behind other bridges further downstream
+    pub fn subordinate_bus_number(&self, access: &impl ConfigRegionAccess) -> u8 {
+        unsafe { access.read(self.0, 0x18) }.get_bits(16..24) as u8
+    }
+
+    /// The range of bus numbers behind the bridge, from the secondary bus to the subordinate bus. Returns `None`
+    /// if the bridge is misprogrammed with a subordinate bus number lower than its secondary bus number, in which
+    /// case the buses behind it should not be scanned.
+    pub fn claimed_buses(&self, access: &impl ConfigRegionAccess) -> Option<RangeInclusive<u8>> {
+        let data = unsafe { access.read(self.0, 0x18) };
+        let secondary = data.get_bits(8..16) as u8;
+        let subordinate = data.get_bits(16..24) as u8;
+        if secondary <= subordinate {
+            Some(secondary..=subordinate)
+        } else {
+            None
+        }
+    }
+
+    /// Will be `true` if the bridge decodes 32-bit I/O addresses, in which case the upper halves of its I/O
+    /// window are held in the I/O Base Upper 16 Bits and I/O Limit Upper 16 Bits registers. Otherwise, it only
+    /// decodes the low 64KiB of I/O space.
+    pub fn supports_32bit_io(&self, access: &impl ConfigRegionAccess) -> bool {
+        unsafe { access.read(self.0, 0x1c) }.get_bits(0..4) == 0x1
+    }
+
+    /// The range of I/O addresses the bridge forwards to its secondary bus, combining the I/O Base and I/O Limit
+    /// registers with their upper halves if the bridge supports 32-bit I/O addressing. The window is 4KiB
+    /// granular. Returns `None` if the window is disabled, which is done by programming the base above the limit.
+    pub fn io_window(&self, access: &impl ConfigRegionAccess) -> Option<RangeInclusive<u32>> {
+        let data = unsafe { access.read(self.0, 0x1c) };
+        let mut base = data.get_bits(4..8) << 12;
+        let mut limit = (data.get_bits(12..16) << 12) | 0xfff;
+        if self.supports_32bit_io(access) {
+            let upper = unsafe { access.read(self.0, 0x30) };
+            base.set_bits(16..32, upper.get_bits(0..16));
+            limit.set_bits(16..32, upper.get_bits(16..32));
+        }
+
+        if base <= limit {
+            Some(base..=limit)
+        } else {
+            None
+        }
+    }
+
+    /// The legacy interrupt pin used by this bridge for its own interrupts (e.g. hot-plug events), as for
+    /// [`EndpointHeader::interrupt_pin`]. Returns `None` if the bridge doesn't use one.
+    pub fn interrupt_pin(&self, access: &impl ConfigRegionAccess) -> Option<InterruptPin> {
+        InterruptPin::try_from(unsafe { access.read_u8(self.0, 0x3d) }).ok()
+    }
+
+    /// The Interrupt Line register of this bridge
+    pub fn interrupt_line(&self, access: &impl ConfigRegionAccess) -> u8 {
+        unsafe { access.read_u8(self.0, 0x3c) }
+    }
+
+    /// Write the Interrupt Line register, leaving the rest of its dword as it is
+    pub fn set_interrupt_line(&self, access: &impl ConfigRegionAccess, line: u8) {
+        /*
+         * The Discard Timer Status bit of the Bridge Control register is RW1C, so it is written as zero rather
+         * than with the value read back.
+         */
+        let mut data = unsafe { access.read(self.0, 0x3c) };
+        data.set_bits(0..8, line as u32);
+        data.set_bit(26, false);
+        unsafe { access.write(self.0, 0x3c, data) }
+    }
+
+    /// Get the Subsystem Vendor ID and Subsystem ID of this bridge. Type-1 headers don't have fields for these,
+    /// so they are read from the bridge subsystem vendor ID capability. Returns `None` if the bridge doesn't have
+    /// this capability.
+    pub fn subsystem(&self, access: &impl ConfigRegionAccess) -> Option<(VendorId, DeviceId)> {
+        self.capabilities(access)
+            .find_map(|capability| match capability {
+                PciCapability::BridgeSubsystemVendorId(capability) => {
+                    Some(capability.subsystem(access))
+                }
+                _ => None,
+            })
+    }
+}
+
+impl From<PciPciBridgeHeader> for PciHeader {
+    fn from(header: PciPciBridgeHeader) -> Self {
+        header.header()
+    }
+}
+
+/// CardBus bridges have a Type-2 header, so the remainder of the header is of the form:
+/// ```ignore
+///     32                           16                              0
+///     +-----------------------------------------------------------+ 0x00
+///     |                                                           |
+///     |                Predefined region of header                |
+///     |                                                           |
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |             CardBus Socket/ExCa Base Address              | 0x10
+///     |                                                           |
+///     +-----------------------------+--------------+--------------+
+///     |     Secondary Status        |   Reserved   |  Capability  | 0x14
+///     |                             |              |   Pointer    |
+///     +--------------+--------------+--------------+--------------+
+///     |   CardBus    | Subordinate  |   CardBus    |     PCI      | 0x18
+///     |Latency Timer | Bus Number   |  Bus Number  |  Bus Number  |
+///     +--------------+--------------+--------------+--------------+
+///     |                  Memory Base Address 0                    | 0x1C
+///     +-----------------------------------------------------------+
+///     |                     Memory Limit 0                        | 0x20
+///     +-----------------------------------------------------------+
+///     |                  Memory Base Address 1                    | 0x24
+///     +-----------------------------------------------------------+
+///     |                     Memory Limit 1                        | 0x28
+///     +-----------------------------------------------------------+
+///     |                   I/O Base Address 0                      | 0x2C
+///     +-----------------------------------------------------------+
+///     |                      I/O Limit 0                          | 0x30
+///     +-----------------------------------------------------------+
+///     |                   I/O Base Address 1                      | 0x34
+///     +-----------------------------------------------------------+
+///     |                      I/O Limit 1                          | 0x38
+///     +-----------------------------+--------------+--------------+
+///     |    Bridge Control           |  Interrupt   | Interrupt    | 0x3C
+///     |                             |     PIN      |   Line       |
+///     +-----------------------------+--------------+--------------+
+///     |      Subsystem Device ID    |     Subsystem Vendor ID     | 0x40
+///     |                             |                             |
+///     +-----------------------------+-----------------------------+
+///     |        16-bit PC Card Legacy Mode Base Address            | 0x44
+///     |                                                           |
+///     +-----------------------------------------------------------+
+/// ```
+pub struct CardBusBridgeHeader(PciAddress);
+
+impl CardBusBridgeHeader {
+    pub fn from_header(
+        header: PciHeader,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<CardBusBridgeHeader> {
+        match header.header_type(access) {
+            HeaderType::CardBusBridge => Some(CardBusBridgeHeader(header.0)),
+            _ => None,
+        }
+    }
+
+    /// The generic view of this function's header. This doesn't access the hardware, so it is cheap to call
+    /// whenever the generic view is needed again.
+    pub fn header(&self) -> PciHeader {
+        PciHeader(self.0)
+    }
+
+    /// Iterate over the capabilities of this bridge, whose Capabilities Pointer is at offset `0x14` rather than
+    /// `0x34`. CardBus bridges are conventional PCI functions, so they have no extended capabilities.
+    pub fn capabilities<'a, T: ConfigRegionAccess>(
+        &self,
+        access: &'a T,
+    ) -> CapabilityIterator<'a, T> {
+        CapabilityIterator::for_header(&self.header(), access)
+    }
+
+    /// Get the Subsystem Vendor ID and Subsystem ID of this bridge. Unlike the other header types, these are at
+    /// offset `0x40`, after the CardBus-specific registers.
+    pub fn subsystem(&self, access: &impl ConfigRegionAccess) -> (VendorId, DeviceId) {
+        let data = unsafe { access.read(self.0, 0x40) };
+        (
+            data.get_bits(0..16) as VendorId,
+            data.get_bits(16..32) as DeviceId,
+        )
+    }
+}
+
+impl From<CardBusBridgeHeader> for PciHeader {
+    fn from(header: CardBusBridgeHeader) -> Self {
+        header.header()
+    }
+}
+
+/// Calculate the size of a memory BAR from the value read back after writing all ones to it, with the flag bits
+/// cleared and extended to 64 bits. Returns `None` if no address bits are writable, which would otherwise
+/// overflow.
+fn bar_size(mask: u64) -> Option<u64> {
+    (!mask).checked_add(1)
+}
+
+pub const MAX_BARS: usize = 6;
+
+/// The index of a BAR slot in a Type-0 header (`0..=5`). Using this instead of a plain integer makes it
+/// impossible to pass a byte offset where a slot was expected.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(transparent)]
+pub struct BarIndex(u8);
+
+impl BarIndex {
+    /// Returns `None` if `index` is past the last BAR slot
+    pub const fn new(index: u8) -> Option<BarIndex> {
+        if (index as usize) < MAX_BARS {
+            Some(BarIndex(index))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(&self) -> u8 {
+        self.0
+    }
+
+    /// Offset of the BAR in configuration space
+    pub const fn offset(&self) -> u16 {
+        0x10 + self.0 as u16 * 4
+    }
+}
+
+impl TryFrom<u8> for BarIndex {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        BarIndex::new(value).ok_or(())
+    }
+}
+
+/// Combine the two dwords of a 64-bit memory BAR into its address. The flag bits in bits `0..4` of `low` (the
+/// memory type and prefetchable bits) are cleared, so raw BAR values can be passed in as they were read.
+pub fn combine_bar64(low: u32, high: u32) -> u64 {
+    let mut address = (low & !0xf) as u64;
+    address.set_bits(32..64, high as u64);
+    address
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Bar {
+    Memory32 {
+        address: u32,
+        size: u32,
+        prefetchable: bool,
+    },
+    Memory64 {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+    },
+    Io {
+        port: u32,
+    },
+}
+
+impl Bar {
+    /// Give access to the memory region decoded by this BAR. This crate doesn't know how to map physical memory,
+    /// so `mapper` is called with the physical address and size of the region, and must return a pointer to
+    /// where it has been mapped. Returns `None` for I/O BARs, which are not memory-mapped.
+    ///
+    /// # Safety
+    /// The pointer returned by `mapper` must be valid for volatile reads and writes of the whole region for as
+    /// long as the returned [`MappedBar`] is used.
+    pub unsafe fn map<T: Copy>(&self, mapper: impl Fn(u64, u64) -> *mut T) -> Option<MappedBar<T>> {
+        let (address, size) = match *self {
+            Bar::Memory32 { address, size, .. } => (address as u64, size as u64),
+            Bar::Memory64 { address, size, .. } => (address, size),
+            Bar::Io { .. } => return None,
+        };
+        Some(MappedBar::new(mapper(address, size), size))
+    }
+
+    /// The range of memory addresses this BAR decodes. Returns `None` for I/O BARs, which don't record their
+    /// size.
+    fn memory_span(&self) -> Option<Range<u64>> {
+        match *self {
+            Bar::Memory32 { address, size, .. } => {
+                Some(address as u64..address as u64 + size as u64)
+            }
+            Bar::Memory64 { address, size, .. } => Some(address..address.saturating_add(size)),
+            Bar::Io { .. } => None,
+        }
+    }
+}
+
+/// Returns `true` if the function is currently decoding the region of `bar`, given the value of its Command
+/// register. Memory BARs are only decoded while Memory Space access is enabled, and I/O BARs while I/O Space access
+/// is enabled.
+pub fn bar_active(bar: &Bar, command: &CommandRegister) -> bool {
+    match bar {
+        Bar::Memory32 { .. } | Bar::Memory64 { .. } => command.memory_space_access_enabled(),
+        Bar::Io { .. } => command.io_space_access_enabled(),
+    }
+}
+
+/// Returns `true` if the two BARs decode any of the same addresses. 32-bit and 64-bit memory BARs are both in
+/// memory space, so are compared with each other, and I/O BARs are compared with each other, but a memory BAR
+/// never overlaps an I/O BAR.
+///
+/// ### Note
+/// [`Bar::Io`] doesn't hold the size of the BAR, so two I/O BARs are only reported as overlapping if they start at
+/// the same port. Compare the [`Resource`]s returned by [`EndpointHeader::resources`] with [`Resource::overlaps`]
+/// to check their whole ranges.
+pub fn bars_overlap(a: &Bar, b: &Bar) -> bool {
+    match (a, b) {
+        (Bar::Io { port: a }, Bar::Io { port: b }) => a == b,
+        _ => match (a.memory_span(), b.memory_span()) {
+            (Some(a), Some(b)) => a.start < b.end && b.start < a.end,
+            _ => false,
+        },
+    }
+}
+
+/// Check a set of BARs (e.g. every BAR assigned on a bus) for overlaps. Returns the indices into `bars` of the
+/// first pair found to overlap, as by [`bars_overlap`].
+pub fn find_bar_overlap(bars: &[Bar]) -> Option<(usize, usize)> {
+    (0..bars.len()).find_map(|i| {
+        (i + 1..bars.len())
+            .find(|&j| bars_overlap(&bars[i], &bars[j]))
+            .map(|j| (i, j))
+    })
+}
+
+/// The kind of address space a [`Resource`] is in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResourceKind {
+    Memory { prefetchable: bool },
+    Io,
+}
+
+/// A region of memory or I/O space decoded by a function, independent of how it is described to software. See
+/// [`EndpointHeader::resources`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Resource {
+    /// The BAR slot the resource occupies or stands in for
+    pub index: u8,
+    pub base: u64,
+    pub size: u64,
+    pub kind: ResourceKind,
+}
+
+impl Resource {
+    /// Returns `true` if the two resources are in the same address space and decode any of the same addresses.
+    /// Memory resources are compared with each other whether or not they are prefetchable.
+    pub fn overlaps(&self, other: &Resource) -> bool {
+        let is_io = |resource: &Resource| resource.kind == ResourceKind::Io;
+        is_io(self) == is_io(other)
+            && self.base < other.base.saturating_add(other.size)
+            && other.base < self.base.saturating_add(self.size)
+    }
+}
+
+/// The address space a memory BAR can be placed in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BarSpace {
+    /// The BAR must be placed below 4GiB
+    Memory32,
+    /// The BAR can be placed anywhere in the 64-bit address space
+    Memory64,
+}
+
+/// A single memory region decoded by a BAR
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BarFootprintEntry {
+    /// The slot of the BAR. For 64-bit BARs, this is the slot holding the lower half of the address.
+    pub index: u8,
+    pub size: u64,
+    /// The alignment the region must be placed at
+    pub alignment: u64,
+    pub space: BarSpace,
+    pub prefetchable: bool,
+}
+
+/// The memory regions decoded by all of a function's BARs. See [`EndpointHeader::total_bar_footprint`].
+#[derive(Clone, Debug)]
+pub struct BarFootprint {
+    entries: [Option<BarFootprintEntry>; MAX_BARS],
+    total: u64,
+}
+
+impl BarFootprint {
+    /// The regions decoded by the function's memory BARs, in slot order
+    pub fn entries(&self) -> impl Iterator<Item = &BarFootprintEntry> {
+        self.entries.iter().flatten()
+    }
+
+    /// The sum of the sizes of all regions
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The strictest alignment required by any of the regions, or `0` if there are none
+    pub fn max_alignment(&self) -> u64 {
+        self.entries()
+            .map(|entry| entry.alignment)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{
+        write_confirmed, AccessEvent, CheckedAccess, LoggingAccess, ReadError, RetryingAccess,
+    };
+    use crate::capability::{
+        parse_capabilities, validate_capabilities, CapabilityError, CapabilityId,
+        CapabilityParseError, DeviceControl2, LtrCapability, LtrLatency, MsiCapability,
+        PciCapability, PcieError, PowerManagementCapability, PowerState, ResizableBarCapability,
+        TriggerMode, MAX_LTR_LATENCY_NS,
+    };
+    use crate::testing::{AccessKind, ConfigSpaceBuilder, TracingAccess};
+
+    fn endpoint() -> EndpointHeader {
+        EndpointHeader(PciAddress::new(0, 0, 0, 0))
+    }
+
+    #[test]
+    fn bar_size_boundaries() {
+        assert_eq!(bar_size(0xffffffff_00000000), Some(1 << 32));
+        assert_eq!(bar_size(0x80000000_00000000), Some(1 << 63));
+        assert_eq!(bar_size(0xffffffff_fffffff0), Some(0x10));
+        assert_eq!(bar_size(0), None);
+    }
+
+    #[test]
+    fn combine_bar64_boundaries() {
+        assert_eq!(combine_bar64(0xffff_ffff, 0), 0xffff_fff0);
+        assert_eq!(combine_bar64(0, 0xffff_ffff), 0xffff_ffff_0000_0000);
+        assert_eq!(
+            combine_bar64(0xffff_ffff, 0xffff_ffff),
+            0xffff_ffff_ffff_fff0
+        );
+        assert_eq!(combine_bar64(0x10, 0), 0x10);
+    }
+
+    #[test]
+    fn largest_32bit_bar() {
+        let space = ConfigSpaceBuilder::new()
+            .bar(0, 0x8)
+            .read_only_bits(0x10, 0x7fffffff)
+            .build();
+        match endpoint().bar(BarIndex::new(0).unwrap(), &space) {
+            Some(Bar::Memory32 {
+                size, prefetchable, ..
+            }) => {
+                assert_eq!(size, 0x80000000);
+                assert!(prefetchable);
+            }
+            bar => panic!("unexpected BAR {:?}", bar),
+        }
+    }
+
+    #[test]
+    fn bar_32bit_of_4gib_is_rejected() {
+        /*
+         * Only the prefetchable bit reads back, so the BAR would have to be 4GiB, which a 32-bit BAR can't be.
+         */
+        let space = ConfigSpaceBuilder::new()
+            .bar(0, 0x8)
+            .read_only_bits(0x10, 0xffffffff)
+            .build();
+        assert!(endpoint().bar(BarIndex::new(0).unwrap(), &space).is_none());
+    }
+
+    #[test]
+    fn full_width_64bit_bar() {
+        let space = ConfigSpaceBuilder::new()
+            .bar(0, 0x4)
+            .read_only_bits(0x10, 0xffffffff)
+            .read_only_bits(0x14, 0x7fffffff)
+            .build();
+        match endpoint().bar(BarIndex::new(0).unwrap(), &space) {
+            Some(Bar::Memory64 { size, .. }) => assert_eq!(size, 1 << 63),
+            bar => panic!("unexpected BAR {:?}", bar),
+        }
+    }
+
+    #[test]
+    fn bar_without_writable_bits() {
+        let space = ConfigSpaceBuilder::new()
+            .read_only_bits(0x10, 0xffffffff)
+            .bar(1, 0x4)
+            .read_only_bits(0x14, 0xffffffff)
+            .read_only_bits(0x18, 0xffffffff)
+            .build();
+        assert!(endpoint().bar(BarIndex::new(0).unwrap(), &space).is_none());
+        assert!(endpoint().bar(BarIndex::new(1).unwrap(), &space).is_none());
+    }
+
+    #[test]
+    fn bar_probe_restores_address() {
+        let space = ConfigSpaceBuilder::new()
+            .bar(0, 0xfebf_0000)
+            .read_only_bits(0x10, 0xfff)
+            .build();
+        match endpoint().bar(BarIndex::new(0).unwrap(), &space) {
+            Some(Bar::Memory32 { address, size, .. }) => {
+                assert_eq!(address, 0xfebf_0000);
+                assert_eq!(size, 0x1000);
+            }
+            bar => panic!("unexpected BAR {:?}", bar),
+        }
+        assert_eq!(unsafe { space.read(endpoint().0, 0x10) }, 0xfebf_0000);
+    }
+
+    #[test]
+    fn misaligned_next_pointer_is_masked() {
+        /*
+         * A power management capability at 0x40 whose next pointer has a reserved bit set, followed by a second
+         * one at 0x50.
+         */
+        let space = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::PowerManagement, &[0; 6])
+            .u32_at(0x40, 0x0000_5101)
+            .u32_at(0x50, 0x0000_0001)
+            .build();
+        let header = endpoint().header();
+        let offsets = endpoint()
+            .capabilities(&space)
+            .map(|capability| capability.address().offset);
+        assert!(offsets.eq([0x40, 0x50]));
+        assert!(parse_capabilities(&space, &header)
+            .map(|capability| capability.unwrap().address().offset)
+            .eq([0x40, 0x50]));
+        assert_eq!(validate_capabilities(&space, &header), Ok(()));
+    }
+
+    /// A quirk for a single device, which sets a bit in a vendor-specific register
+    struct PokeQuirk;
+
+    impl Quirk for PokeQuirk {
+        fn matches(&self, vendor: VendorId, device: DeviceId) -> bool {
+            vendor == 0x8086 && device == 0x1234
+        }
+
+        fn apply<A: ConfigRegionAccess>(&self, header: &EndpointHeader, access: &A) {
+            unsafe {
+                let value = access.read(header.0, 0x40);
+                access.write(header.0, 0x40, value | 1);
+            }
+        }
+    }
+
+    /// A quirk for every device from one vendor, which must never be applied
+    struct OtherVendorQuirk;
+
+    impl Quirk for OtherVendorQuirk {
+        fn matches(&self, vendor: VendorId, _device: DeviceId) -> bool {
+            vendor == 0x1002
+        }
+
+        fn apply<A: ConfigRegionAccess>(&self, _header: &EndpointHeader, _access: &A) {
+            panic!("quirk applied to a device from another vendor");
+        }
+    }
+
+    #[test]
+    fn quirk_is_applied_to_matching_device() {
+        let access = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .vendor(0x8086)
+                .device(0x1234)
+                .build(),
+        );
+        assert!(endpoint().apply_quirk(&access, &PokeQuirk));
+        assert_eq!(access.writes(), [(0x40, 1)]);
+    }
+
+    #[test]
+    fn quirk_is_not_applied_to_other_devices() {
+        let access = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .vendor(0x8086)
+                .device(0x5678)
+                .build(),
+        );
+        assert!(!endpoint().apply_quirk(&access, &PokeQuirk));
+        assert!(!endpoint().apply_quirk(&access, &OtherVendorQuirk));
+        assert!(access.writes().is_empty());
+    }
+
+    #[test]
+    fn enumerate_skips_unknown_header_types() {
+        /*
+         * The synthetic configuration space answers for every address, so every device on bus 0 is found.
+         */
+        let endpoints = ConfigSpaceBuilder::new().vendor(0x8086).build();
+        let mut visited = 0;
+        assert_eq!(
+            enumerate(&endpoints, 0, EnumerationOrder::DepthFirst, |_| visited +=
+                1),
+            0
+        );
+        assert_eq!(visited, 32);
+
+        let unknown = ConfigSpaceBuilder::new()
+            .vendor(0x8086)
+            .header_type(HeaderType::Unknown(0x7f))
+            .build();
+        let mut visited = 0;
+        assert_eq!(
+            enumerate(&unknown, 0, EnumerationOrder::DepthFirst, |_| visited += 1),
+            32
+        );
+        assert_eq!(visited, 0);
+    }
+
+    #[test]
+    fn capability_past_the_end_is_reported() {
+        /*
+         * A PCI Express capability at 0xf0 would need 0x3c bytes.
+         */
+        let space = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::PowerManagement, &[0; 6])
+            .u32_at(0x40, 0x0000_f001)
+            .u32_at(0xf0, 0x0002_0010)
+            .build();
+        let header = endpoint().header();
+        let mut capabilities = parse_capabilities(&space, &header);
+        assert!(matches!(
+            capabilities.next(),
+            Some(Ok(PciCapability::PowerManagement(_)))
+        ));
+        assert_eq!(
+            capabilities.next().map(|capability| capability.err()),
+            Some(Some(CapabilityParseError::OutOfBounds {
+                offset: 0xf0,
+                id: CapabilityId::PciExpress,
+                length: 0x3c
+            }))
+        );
+        assert!(capabilities.next().is_none());
+        assert_eq!(
+            validate_capabilities(&space, &header),
+            Err(CapabilityError::OutOfBounds {
+                offset: 0xf0,
+                length: 0x3c
+            })
+        );
+    }
+
+    fn msi(space: &impl ConfigRegionAccess) -> MsiCapability {
+        match endpoint().capabilities(space).next() {
+            Some(PciCapability::Msi(msi)) => msi,
+            capability => panic!("unexpected capability {:?}", capability),
+        }
+    }
+
+    #[test]
+    fn msi_registers_follow_address_width() {
+        /*
+         * Per-vector masking, with a 32-bit and then a 64-bit message address.
+         */
+        for (control, data_offset) in [(0x0100u16, 0x48u16), (0x0180, 0x4c)] {
+            let mut body = [0; 22];
+            body[..2].copy_from_slice(&control.to_le_bytes());
+            let access = TracingAccess::new(
+                ConfigSpaceBuilder::new()
+                    .add_capability(CapabilityId::Msi, &body)
+                    .u32_at(data_offset as usize + 8, 0x5)
+                    .build(),
+            );
+            let msi = msi(&access);
+            msi.set_message_info(0xfee0_0000, 0x30, TriggerMode::Edge, &access);
+            msi.set_message_mask(&access, 0x3);
+            assert_eq!(
+                access.writes(),
+                [
+                    (0x44, 0xfee0_0000),
+                    (data_offset, 0x30),
+                    (data_offset + 4, 0x3)
+                ]
+            );
+            assert_eq!(msi.get_message_mask(&access), 0x3);
+            assert_eq!(msi.get_pending(&access), 0x5);
+        }
+    }
+
+    #[test]
+    fn snapshot_restores_msi_and_msix() {
+        /*
+         * A 32-bit MSI capability with per-vector masking at 0x40, and an MSI-X capability at 0x58.
+         */
+        let configured = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::Msi, &[0; 22])
+            .add_capability(CapabilityId::MsiX, &[0; 10])
+            .u32_at(0x40, 0x0101_5805)
+            .u32_at(0x44, 0xfee0_0000)
+            .u32_at(0x48, 0x0030)
+            .u32_at(0x4c, 0x3)
+            .u32_at(0x58, 0x8000_0011)
+            .build();
+        let snapshot = endpoint().save_state(&configured);
+        assert!(snapshot.is_complete());
+
+        let reset = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::Msi, &[0; 22])
+            .add_capability(CapabilityId::MsiX, &[0; 10])
+            .u32_at(0x40, 0x0100_5805)
+            .build();
+        endpoint().restore_state(&reset, &snapshot);
+        for offset in [0x40, 0x44, 0x48, 0x4c, 0x58] {
+            assert_eq!(unsafe { reset.read(endpoint().0, offset) }, unsafe {
+                configured.read(endpoint().0, offset)
+            });
+        }
+    }
+
+    #[test]
+    fn snapshot_reports_overflow() {
+        /*
+         * Each PCI Express capability (version 2, with a slot) has five control registers to save.
+         */
+        let space = (0..4)
+            .fold(ConfigSpaceBuilder::new(), |builder, _| {
+                builder.add_capability(
+                    CapabilityId::PciExpress,
+                    &[0x42, 0x01, 0, 0, 0, 0, 0, 0, 0, 0],
+                )
+            })
+            .build();
+        assert!(!endpoint().save_state(&space).is_complete());
+    }
+
+    #[test]
+    fn pcie_flr_waits_for_the_function() {
+        let space = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::PciExpress, &[0x02, 0x00, 0, 0, 0, 0x10])
+            .build();
+        let access = TracingAccess::new(space);
+        let mut waits = alloc::vec::Vec::new();
+        assert!(matches!(
+            endpoint().reset(&access, |ms| waits.push(ms)),
+            Ok(ResetMethod::PcieFlr(_))
+        ));
+        assert_eq!(access.writes(), [(0x48, 0x8000)]);
+        assert_eq!(waits, [100]);
+    }
+
+    #[test]
+    fn pcie_flr_is_not_initiated_with_transactions_pending() {
+        let space = ConfigSpaceBuilder::new()
+            .add_capability(CapabilityId::PciExpress, &[0x02, 0x00, 0, 0, 0, 0x10])
+            .u32_at(0x48, 0x0020_0000)
+            .build();
+        let access = TracingAccess::new(space);
+        assert_eq!(
+            endpoint().reset(&access, |_| ()).err(),
+            Some(ResetError::TransactionsPending)
+        );
+        assert!(access.writes().is_empty());
+    }
+
+    #[test]
+    fn rom_probe_disables_decoding() {
+        let access = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .u32_at(0x30, 0xfe00_0001)
+                .read_only_bits(0x30, 0x0000_fffe)
+                .build(),
+        );
+        let rom = endpoint().expansion_rom(&access).unwrap();
+        assert_eq!((rom.base(), rom.size()), (0xfe00_0000, 0x10000));
+        assert_eq!(access.writes(), [(0x30, 0xffff_f800), (0x30, 0xfe00_0001)]);
+    }
+
+    #[test]
+    fn rom_without_image_is_restored() {
+        let space = ConfigSpaceBuilder::new()
+            .command(0x0002)
+            .u32_at(0x30, 0xfe00_0000)
+            .read_only_bits(0x30, 0x0000_07fe)
+            .build();
+        let rom = endpoint().expansion_rom(&space).unwrap();
+        let image = [0u8; 0x800];
+        assert_eq!(
+            unsafe { rom.read_image(&space, |_, _| image.as_ptr()) }.err(),
+            Some(RomError::InvalidSignature)
+        );
+        assert_eq!(unsafe { space.read(endpoint().0, 0x30) }, 0xfe00_0000);
+        assert_eq!(unsafe { space.read(endpoint().0, 0x04) }, 0x0002);
+    }
+
+    /// Counts up on every read, so no two reads ever agree
+    struct UnstableAccess(core::cell::Cell<u32>);
+
+    impl ConfigRegionAccess for UnstableAccess {
+        fn function_exists(&self, _address: PciAddress) -> bool {
+            true
+        }
+
+        unsafe fn read(&self, _address: PciAddress, _offset: u16) -> u32 {
+            self.0.set(self.0.get() + 1);
+            self.0.get()
+        }
+
+        unsafe fn write(&self, _address: PciAddress, _offset: u16, _value: u32) {}
+    }
+
+    #[test]
+    fn unstable_read_returns_last_value() {
+        let access = RetryingAccess::new(UnstableAccess(core::cell::Cell::new(0)), 2);
+        assert_eq!(
+            unsafe { access.try_read(endpoint().0, 0) },
+            Err(ReadError::Unstable { last: 4 })
+        );
+        assert_eq!(unsafe { access.read(endpoint().0, 0) }, 8);
+        assert_eq!(access.into_inner().0.get(), 8);
+    }
+
+    #[test]
+    fn write_confirmed_checks_masked_bits() {
+        let space = ConfigSpaceBuilder::new()
+            .read_only_bits(0x40, 0xff00)
+            .build();
+        let address = endpoint().0;
+        assert!(unsafe { write_confirmed(&space, address, 0x40, 0x1234, 0xff) });
+        assert!(!unsafe { write_confirmed(&space, address, 0x40, 0x1234, 0xffff) });
+    }
+
+    #[test]
+    fn device_control_2_needs_version_2() {
+        for (version, expected) in [(1, Err(PcieError::NotInVersion1)), (2, Ok(()))] {
+            let mut body = [0; 0x3a];
+            body[0] = version;
+            let access = TracingAccess::new(
+                ConfigSpaceBuilder::new()
+                    .add_capability(CapabilityId::PciExpress, &body)
+                    .build(),
+            );
+            let pcie = match endpoint().capabilities(&access).next() {
+                Some(PciCapability::PciExpress(pcie)) => pcie,
+                capability => panic!("unexpected capability {:?}", capability),
+            };
+            let result = pcie.set_device_control_2(&access, DeviceControl2::new(0x400));
+            assert_eq!(result, expected);
+            let writes = if result.is_ok() {
+                &[(0x68, 0x400)][..]
+            } else {
+                &[]
+            };
+            assert_eq!(access.writes(), writes);
+        }
+    }
+
+    #[test]
+    fn checked_access_forwards_narrow_accesses() {
+        let mut widths = alloc::vec::Vec::new();
+        let space = ConfigSpaceBuilder::new().vendor(0x8086).build();
+        let access = CheckedAccess::new(LoggingAccess::new(space, |event: AccessEvent| {
+            widths.push((event.kind, event.width))
+        }));
+        unsafe {
+            assert_eq!(access.read_u8(endpoint().0, 0x0), 0x86);
+            assert_eq!(access.read_u16(endpoint().0, 0x0), 0x8086);
+            access.write_u8(endpoint().0, 0x3c, 0xb);
+            access.write_u16(endpoint().0, 0x04, 0x6);
+        }
+        assert_eq!(
+            widths,
+            [
+                (AccessKind::Read, 1),
+                (AccessKind::Read, 2),
+                (AccessKind::Write, 1),
+                (AccessKind::Write, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn bar_equivalent_decode() {
+        let decoded: alloc::vec::Vec<_> = (0..=15).map(BarEquivalent::from).collect();
+        assert_eq!(
+            decoded,
+            [
+                BarEquivalent::Bar(0),
+                BarEquivalent::Bar(1),
+                BarEquivalent::Bar(2),
+                BarEquivalent::Bar(3),
+                BarEquivalent::Bar(4),
+                BarEquivalent::Bar(5),
+                BarEquivalent::BehindBridge,
+                BarEquivalent::None,
+                BarEquivalent::ExpansionRom,
+                BarEquivalent::VfBar(0),
+                BarEquivalent::VfBar(1),
+                BarEquivalent::VfBar(2),
+                BarEquivalent::VfBar(3),
+                BarEquivalent::VfBar(4),
+                BarEquivalent::VfBar(5),
+                BarEquivalent::Reserved(15),
+            ]
+        );
+    }
+
+    fn memory32(address: u32, size: u32) -> Bar {
+        Bar::Memory32 {
+            address,
+            size,
+            prefetchable: false,
+        }
+    }
+
+    fn memory64(address: u64, size: u64) -> Bar {
+        Bar::Memory64 {
+            address,
+            size,
+            prefetchable: true,
+        }
+    }
+
+    #[test]
+    fn adjacent_bars_do_not_overlap() {
+        assert!(!bars_overlap(
+            &memory32(0x1000, 0x1000),
+            &memory32(0x2000, 0x1000)
+        ));
+        assert!(!bars_overlap(
+            &memory32(0x2000, 0x1000),
+            &memory32(0x1000, 0x1000)
+        ));
+    }
+
+    #[test]
+    fn nested_bars_overlap() {
+        assert!(bars_overlap(
+            &memory32(0x1000, 0x4000),
+            &memory32(0x2000, 0x1000)
+        ));
+        assert!(bars_overlap(
+            &memory32(0x2000, 0x1000),
+            &memory32(0x1000, 0x4000)
+        ));
+    }
+
+    #[test]
+    fn memory32_and_memory64_bars_share_memory_space() {
+        assert!(bars_overlap(
+            &memory32(0xfe00_0000, 0x10_0000),
+            &memory64(0xfe08_0000, 0x1000)
+        ));
+        assert!(!bars_overlap(
+            &memory32(0xfe00_0000, 0x10_0000),
+            &memory64(0x1_fe00_0000, 0x1000)
+        ));
+    }
+
+    #[test]
+    fn memory_and_io_bars_never_overlap() {
+        assert!(!bars_overlap(
+            &memory32(0x400, 0x100),
+            &Bar::Io { port: 0x400 }
+        ));
+        assert!(!bars_overlap(
+            &Bar::Io { port: 0x400 },
+            &memory64(0x400, 0x100)
+        ));
+    }
+
+    #[test]
+    fn io_bars_at_the_same_port_overlap() {
+        assert!(bars_overlap(
+            &Bar::Io { port: 0x400 },
+            &Bar::Io { port: 0x400 }
+        ));
+        assert!(!bars_overlap(
+            &Bar::Io { port: 0x400 },
+            &Bar::Io { port: 0x500 }
+        ));
+    }
+
+    #[test]
+    fn bar_at_the_top_of_memory() {
+        /*
+         * The end of this BAR doesn't fit in a `u64`, so it is saturated.
+         */
+        let top = memory64(u64::MAX - 0xfff, 0x1000);
+        assert!(bars_overlap(&top, &memory64(u64::MAX - 0x7ff, 0x800)));
+        assert!(!bars_overlap(&top, &memory64(u64::MAX - 0x1fff, 0x1000)));
+        assert_eq!(
+            find_bar_overlap(&[
+                memory32(0x1000, 0x1000),
+                top,
+                memory64(u64::MAX - 0xf, 0x10)
+            ]),
+            Some((1, 2))
+        );
+    }
+
+    /// A configuration space with an LTR capability at `0x100`, whose latency register holds `latencies`
+    fn ltr_space(latencies: u32) -> testing::ConfigSpace {
+        ConfigSpaceBuilder::new()
+            .u32_at(0x100, 0x0001_0018)
+            .u32_at(0x104, latencies)
+            .build()
+    }
+
+    fn ltr(space: &impl ConfigRegionAccess) -> LtrCapability {
+        let capability = endpoint().extended_capabilities(space).next().unwrap();
+        LtrCapability::new(&capability).unwrap()
+    }
+
+    /// The raw register bits `nanos` is encoded as
+    fn encode_ltr(nanos: u64) -> Option<u32> {
+        let space = ltr_space(0);
+        ltr(&space).set_max_snoop_latency(&space, LtrLatency::from_nanos(nanos)?);
+        Some(unsafe { space.read(endpoint().0, 0x104) })
+    }
+
+    #[test]
+    fn ltr_latency_encoding() {
+        /*
+         * The value is in bits 0..10, and the scale in bits 10..13.
+         */
+        assert_eq!(encode_ltr(1023), Some(1023));
+        assert_eq!(encode_ltr(1024), Some(32 | 1 << 10));
+        assert_eq!(encode_ltr(1025), Some(33 | 1 << 10));
+        assert_eq!(encode_ltr(MAX_LTR_LATENCY_NS), Some(1023 | 5 << 10));
+        assert_eq!(encode_ltr(MAX_LTR_LATENCY_NS + 1), None);
+        assert_eq!(LtrLatency::from_nanos(1025).unwrap().as_nanos(), 33 * 32);
+        assert_eq!(
+            LtrLatency::from_nanos(MAX_LTR_LATENCY_NS)
+                .unwrap()
+                .as_nanos(),
+            MAX_LTR_LATENCY_NS
+        );
+    }
+
+    #[test]
+    fn ltr_reserved_scales_decode_as_zero() {
+        for scale in [6, 7] {
+            let space = ltr_space(1 | scale << 10);
+            assert_eq!(ltr(&space).max_snoop_latency(&space).as_nanos(), 0);
+        }
+    }
+
+    #[test]
+    fn ltr_set_snoop_keeps_no_snoop() {
+        let space = ltr_space(0x1234 << 16);
+        let ltr = ltr(&space);
+        ltr.set_max_snoop_latency(&space, LtrLatency::from_nanos(1023).unwrap());
+        assert_eq!(unsafe { space.read(endpoint().0, 0x104) }, 0x1234_03ff);
+        assert_eq!(ltr.max_no_snoop_latency(&space).as_nanos(), 0x234 << 20);
+    }
+
+    #[test]
+    fn linear_index_round_trips() {
+        for (segment, index) in [
+            (0, 0),
+            (0, PciAddress::ADDRESSES_PER_SEGMENT - 1),
+            (3, 0x1234),
+        ] {
+            let address = PciAddress::from_linear_index(segment, index).unwrap();
+            assert_eq!(address.segment(), segment);
+            assert_eq!(address.linear_index(), index);
+        }
+        assert_eq!(
+            PciAddress::from_linear_index(0, PciAddress::ADDRESSES_PER_SEGMENT),
+            None
+        );
+    }
+
+    #[test]
+    fn linear_index_orders_by_bus_device_function() {
+        assert_eq!(PciAddress::new(7, 0, 0, 1).linear_index(), 1);
+        assert_eq!(PciAddress::new(7, 0, 1, 0).linear_index(), 8);
+        assert_eq!(PciAddress::new(7, 1, 0, 0).linear_index(), 8 * 32);
+        assert_eq!(
+            PciAddress::new(7, 255, 31, 7).linear_index(),
+            PciAddress::ADDRESSES_PER_SEGMENT - 1
+        );
+        assert!(
+            PciAddress::new(0, 1, 0, 0).linear_index()
+                > PciAddress::new(0, 0, 31, 7).linear_index()
+        );
+        assert!(
+            PciAddress::new(0, 0, 1, 0).linear_index() > PciAddress::new(0, 0, 0, 7).linear_index()
+        );
+    }
+
+    /// A power management capability at `0x40`, with its PMCSR at `0x44` holding `pmcsr`
+    fn pm(
+        pmcsr: u32,
+    ) -> (
+        TracingAccess<testing::ConfigSpace>,
+        PowerManagementCapability,
+    ) {
+        let access = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .add_capability(CapabilityId::PowerManagement, &[0; 6])
+                .u32_at(0x44, pmcsr)
+                .build(),
+        );
+        let pm = match endpoint().capabilities(&access).next() {
+            Some(PciCapability::PowerManagement(pm)) => pm,
+            capability => panic!("unexpected capability {:?}", capability),
+        };
+        (access, pm)
+    }
+
+    #[test]
+    fn set_power_state_leaves_pme_pending() {
+        /*
+         * PME_Status, PME_En and Data_Select 3.
+         */
+        let (access, pm) = pm(0x8700);
+        pm.set_power_state(&access, PowerState::D3Hot);
+        assert_eq!(access.writes(), [(0x44, 0x0703)]);
+    }
+
+    #[test]
+    fn clear_pme_status_keeps_power_state() {
+        let (access, pm) = pm(0x8103);
+        pm.clear_pme_status(&access);
+        assert_eq!(access.writes(), [(0x44, 0x8103)]);
+    }
+
+    #[test]
+    fn accessors_match_header_layout() {
+        let field = |name: &str| {
+            let (_, offset, width) = ENDPOINT_HEADER_LAYOUT
+                .iter()
+                .find(|(field, _, _)| *field == name)
+                .unwrap();
+            (*offset as usize, *width as usize)
+        };
+        let mut bytes = [0u8; 0x40];
+        let mut plant = |name: &str, value: u32| {
+            let (offset, width) = field(name);
+            bytes[offset..offset + width].copy_from_slice(&value.to_le_bytes()[..width]);
+        };
+        plant("Vendor ID", 0x8086);
+        plant("Device ID", 0x1234);
+        plant("Status", 0x0010);
+        plant("Revision ID", 0x05);
+        plant("Class Code", 0x02_01_03);
+        plant("Cache Line Size", 0x10);
+        plant("Subsystem Vendor ID", 0x1af4);
+        plant("Subsystem ID", 0x0042);
+        plant("Capabilities Pointer", 0x48);
+        plant("Interrupt Line", 0x0b);
+        plant("Interrupt Pin", 0x02);
+        let space = bytes
+            .chunks(4)
+            .enumerate()
+            .fold(ConfigSpaceBuilder::new(), |builder, (i, dword)| {
+                builder.u32_at(
+                    i * 4,
+                    u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]]),
+                )
+            })
+            .build();
+
+        let header = endpoint().header();
+        assert_eq!(header.id(&space), (0x8086, 0x1234));
+        assert_eq!(header.revision_and_class(&space), (0x05, 0x02, 0x01, 0x03));
+        assert_eq!(header.cache_line_size(&space).as_dwords(), 0x10);
+        assert_eq!(endpoint().capability_pointer(&space), 0x48);
+        assert_eq!(endpoint().subsystem(&space), (0x1af4, 0x0042));
+        assert_eq!(endpoint().interrupt_line(&space), 0x0b);
+        assert_eq!(endpoint().interrupt_pin(&space), Some(InterruptPin::IntB));
+    }
+
+    #[test]
+    fn resizable_bar_sizes() {
+        const MIB: u64 = 1 << 20;
+        const TIB: u64 = 1 << 40;
+        /*
+         * BAR 0, the only entry, supports 1MiB (capability bit 4), 128TiB (capability bit 31) and 256TiB (control
+         * bit 16).
+         */
+        let space = ConfigSpaceBuilder::new()
+            .u32_at(0x100, 0x0001_0015)
+            .u32_at(0x104, 1 << 4 | 1 << 31)
+            .u32_at(0x108, 1 << 16 | 1 << 5)
+            .build();
+        let capability = endpoint().extended_capabilities(&space).next().unwrap();
+        let rebar = ResizableBarCapability::new(&capability).unwrap();
+        let entry = rebar.entry(&space, BarIndex::new(0).unwrap()).unwrap();
+        assert_eq!(entry.supported_sizes, 1 | 1 << 27 | 1 << 28);
+        assert!(entry.sizes().eq([MIB, 128 * TIB, 256 * TIB]));
+        assert!(entry.sizes().all(|size| entry.supports(size)));
+        assert!(!entry.supports(2 * MIB));
+        assert!(!entry.supports(512 * TIB));
+        assert!(!entry.supports(3 * MIB));
+
+        rebar
+            .set_size(&space, BarIndex::new(0).unwrap(), 256 * TIB)
+            .unwrap();
+        assert_eq!(
+            unsafe { space.read(endpoint().0, 0x108) }.get_bits(8..14),
+            28
+        );
+        assert_eq!(
+            rebar
+                .entry(&space, BarIndex::new(0).unwrap())
+                .unwrap()
+                .current_size,
+            256 * TIB
+        );
+    }
+
+    #[test]
+    fn read_header_type_reads_one_byte() {
+        let space = ConfigSpaceBuilder::new()
+            .header_type(HeaderType::PciPciBridge)
+            .multifunction(true)
+            .build();
+        let mut reads = alloc::vec::Vec::new();
+        let access = LoggingAccess::new(space, |event: AccessEvent| {
+            reads.push((event.offset, event.width))
+        });
+        assert_eq!(
+            read_header_type(&access, endpoint().0),
+            (HeaderType::PciPciBridge, true)
+        );
+        assert_eq!(reads, [(0x0e, 1)]);
+    }
+
+    #[test]
+    fn dump_does_not_write() {
+        let access = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .bar(0, 0xfebf_000c)
+                .bar(1, 0x1)
+                .bar(2, 0x1001)
+                .build(),
+        );
+        let mut out = alloc::string::String::new();
+        endpoint().dump(&access, &mut out).unwrap();
+        assert!(access.writes().is_empty());
+        assert!(out.contains("BAR0: Memory at 0x1febf0000 (64-bit, prefetchable)"));
+        assert!(!out.contains("BAR1"));
+        assert!(out.contains("BAR2: I/O ports at 0x1000"));
+    }
+
+    #[test]
+    fn io_bar_size_is_only_in_resources() {
+        /*
+         * The other BARs are hardwired to zero, as they would be on a function that doesn't implement them.
+         */
+        let space = (0x14..0x28)
+            .step_by(4)
+            .fold(ConfigSpaceBuilder::new(), |builder, offset| {
+                builder.read_only_bits(offset, 0xffffffff)
+            })
+            .bar(0, 0x1001)
+            .read_only_bits(0x10, 0x1f)
+            .build();
+        assert!(matches!(
+            endpoint().bar(BarIndex::new(0).unwrap(), &space),
+            Some(Bar::Io { port: 0x400 })
+        ));
+        let resources: alloc::vec::Vec<Resource> = endpoint().resources(&space).collect();
+        assert_eq!(
+            resources,
+            [Resource {
+                index: 0,
+                base: 0x1000,
+                size: 0x20,
+                kind: ResourceKind::Io,
+            }]
+        );
+        assert_eq!(unsafe { space.read(endpoint().0, 0x10) }, 0x1001);
+    }
+
+    #[test]
+    fn reserved_bar_type_is_not_decoded() {
+        let space = ConfigSpaceBuilder::new().bar(0, 0x6).build();
+        assert!(endpoint().bar(BarIndex::new(0).unwrap(), &space).is_none());
+    }
+
+    #[test]
+    fn bar_64bit_in_last_slot() {
+        let space = TracingAccess::new(
+            ConfigSpaceBuilder::new()
+                .bar(5, 0x4)
+                .u32_at(0x28, 0x1234_5678)
+                .build(),
+        );
+        assert!(endpoint().bar(BarIndex::new(5).unwrap(), &space).is_none());
+        assert!(space
+            .trace()
+            .iter()
+            .all(|entry| entry.offset != 0x28 || entry.kind == AccessKind::Read));
+        assert_eq!(unsafe { space.read(endpoint().0, 0x28) }, 0x1234_5678);
+    }
 }
@@ -0,0 +1,59 @@
+use core::{marker::PhantomData, mem, ptr};
+
+/// Error returned when an access through a [`MappedBar`] would fall outside the region decoded by the BAR, or is
+/// not aligned to the size of the access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+/// The region decoded by a memory BAR, mapped into the address space by [`crate::Bar::map`]. All accesses through
+/// it are volatile, and are checked against the size of the BAR.
+pub struct MappedBar<T> {
+    base: *mut u8,
+    size: u64,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T: Copy> MappedBar<T> {
+    /// # Safety
+    /// `base` must be valid for volatile reads and writes of `size` bytes for as long as the `MappedBar` lives.
+    pub(crate) unsafe fn new(base: *mut T, size: u64) -> MappedBar<T> {
+        MappedBar {
+            base: base as *mut u8,
+            size,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Size of the region in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Pointer to the start of the region, for accesses this type doesn't cover
+    pub fn as_ptr(&self) -> *mut T {
+        self.base as *mut T
+    }
+
+    /// Read the `T` at byte `offset` into the region
+    pub fn read(&self, offset: u64) -> Result<T, OutOfBounds> {
+        let pointer = self.pointer(offset)?;
+        Ok(unsafe { ptr::read_volatile(pointer) })
+    }
+
+    /// Write `value` to byte `offset` into the region
+    pub fn write(&self, offset: u64, value: T) -> Result<(), OutOfBounds> {
+        let pointer = self.pointer(offset)?;
+        unsafe { ptr::write_volatile(pointer, value) };
+        Ok(())
+    }
+
+    fn pointer(&self, offset: u64) -> Result<*mut T, OutOfBounds> {
+        let end = offset
+            .checked_add(mem::size_of::<T>() as u64)
+            .ok_or(OutOfBounds)?;
+        if end > self.size || !offset.is_multiple_of(mem::align_of::<T>() as u64) {
+            return Err(OutOfBounds);
+        }
+        Ok(unsafe { self.base.add(offset as usize) } as *mut T)
+    }
+}
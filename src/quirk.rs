@@ -0,0 +1,13 @@
+use crate::{ConfigRegionAccess, DeviceId, EndpointHeader, VendorId};
+
+/// A device-specific fixup, e.g. a vendor-specific configuration write that some devices need before they behave
+/// as the specification says they should. Quirks are applied with [`EndpointHeader::apply_quirk`], which keeps the
+/// knowledge of individual devices out of this crate.
+pub trait Quirk {
+    /// Returns `true` if the quirk should be applied to functions with the given IDs. A quirk that really is
+    /// meant for every function has to say so by returning `true` here.
+    fn matches(&self, vendor: VendorId, device: DeviceId) -> bool;
+
+    /// Apply the quirk to a function whose IDs matched
+    fn apply<A: ConfigRegionAccess>(&self, header: &EndpointHeader, access: &A);
+}
@@ -1,6 +1,7 @@
 use bit_field::BitField;
 use core::convert::TryFrom;
-use core::fmt::{Debug, Formatter};
+use core::fmt::{Debug, Formatter, LowerHex};
+use core::ops::{BitAnd, Not};
 
 /// Slowest time that a device will assert DEVSEL# for any bus command except Configuration Space
 /// read and writes
@@ -24,14 +25,62 @@ impl TryFrom<u8> for DevselTiming {
     }
 }
 
+/// A configuration register decoded as a newtype over its raw value. This lets generic code (e.g. for dumping or
+/// diffing registers) handle any register without knowing its concrete type.
+pub trait ConfigRegister: Copy {
+    /// The integer type the register is stored as
+    type Raw: Copy;
+
+    /// Raw value of the register. Together with [`bit_field::BitField`], this can be used to read bits this crate
+    /// doesn't decode, e.g. reserved or vendor-defined bits of the Command and Status registers.
+    fn raw(&self) -> Self::Raw;
+
+    fn from_raw(raw: Self::Raw) -> Self;
+
+    /// The bits of the register that are reserved by the specification, which should be ignored when reading and
+    /// preserved when writing
+    fn reserved_mask() -> Self::Raw;
+
+    /// A copy of the register with its reserved bits cleared. Reserved bits can read as `1` on some hardware, so
+    /// two values should be normalized before being compared.
+    fn normalized(&self) -> Self
+    where
+        Self::Raw: BitAnd<Output = Self::Raw> + Not<Output = Self::Raw>,
+    {
+        Self::from_raw(self.raw() & !Self::reserved_mask())
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub struct StatusRegister(u16);
 
 impl StatusRegister {
+    /// Bits of the Status register that are cleared by writing `1` to them (RW1C). Writing `0` to these bits has
+    /// no effect.
+    pub const RW1C_MASK: u16 = 0xf900;
+
+    /// Bits of the Status register that describe what the function is capable of, and so don't change while it
+    /// is running: Capabilities List, 66 MHz Capable, Fast Back-to-Back Capable and DEVSEL Timing. The other
+    /// defined bits are volatile: the RW1C error bits in [`StatusRegister::RW1C_MASK`] are set by hardware as
+    /// errors occur, and Interrupt Status follows the INTx# signal.
+    pub const STATIC_MASK: u16 = 0x06b0;
+
     pub fn new(value: u16) -> Self {
         StatusRegister(value)
     }
 
+    /// Compose the dword to write to offset `0x04` of the header in order to clear the RW1C bits set in `bits`,
+    /// while leaving the Command register (the lower half of the dword) set to `command`. Bits of `bits` that
+    /// are not RW1C are ignored.
+    ///
+    /// This allows status bits to be acknowledged with a single configuration write, without the
+    /// read-modify-write of the Status register clobbering the Command register or clearing other status bits.
+    pub fn clear_dword(command: u16, bits: u16) -> u32 {
+        let mut dword = command as u32;
+        dword.set_bits(16..32, (bits & Self::RW1C_MASK) as u32);
+        dword
+    }
+
     /// Will be `true` whenever the device detects a parity error, even if parity error handling is disabled.
     pub fn parity_error_detected(&self) -> bool {
         self.0.get_bit(15)
@@ -62,6 +111,7 @@ impl StatusRegister {
     /// Configuration Space read and writes.
     ///
     /// For PCIe always set to `Fast`
+    #[allow(clippy::result_unit_err)]
     pub fn devsel_timing(&self) -> Result<DevselTiming, ()> {
         let bits = self.0.get_bits(9..11);
         DevselTiming::try_from(bits as u8)
@@ -104,6 +154,32 @@ impl StatusRegister {
     pub fn interrupt_status(&self) -> bool {
         self.0.get_bit(3)
     }
+
+    /// Compare two Status register values, only looking at the bits in [`StatusRegister::STATIC_MASK`]. This
+    /// can be used to tell whether a function has changed between two reads of its Status register (e.g. by
+    /// being hot-swapped), without errors or interrupts being raised in the meantime counting as a change.
+    pub fn eq_ignoring_volatile(&self, other: &StatusRegister) -> bool {
+        self.0 & Self::STATIC_MASK == other.0 & Self::STATIC_MASK
+    }
+}
+
+impl ConfigRegister for StatusRegister {
+    type Raw = u16;
+
+    fn raw(&self) -> u16 {
+        self.0
+    }
+
+    fn from_raw(raw: u16) -> Self {
+        StatusRegister(raw)
+    }
+
+    fn reserved_mask() -> u16 {
+        /*
+         * Bit 6 used to be the UDF Supported bit, but has been reserved since PCI 2.2.
+         */
+        0x0047
+    }
 }
 
 impl Debug for StatusRegister {
@@ -126,3 +202,271 @@ impl Debug for StatusRegister {
             .finish()
     }
 }
+
+/// Formats the raw value of the register, so a function with only a capability list reports `0x0010` for
+/// `{:#06x}`
+impl LowerHex for StatusRegister {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// The Command register, which controls how the device responds to and generates PCI cycles. Modify it with a
+/// [`CommandRegisterBuilder`].
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct CommandRegister(u16);
+
+impl CommandRegister {
+    pub fn new(value: u16) -> Self {
+        CommandRegister(value)
+    }
+
+    /// Start building a new Command register value, using this one as the starting point
+    pub fn builder(&self) -> CommandRegisterBuilder {
+        CommandRegisterBuilder::new(*self)
+    }
+
+    /// If returns `true` the device can respond to I/O Space accesses; otherwise, the device's response is
+    /// disabled.
+    pub fn io_space_access_enabled(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    /// If returns `true` the device can respond to Memory Space accesses; otherwise, the device's response is
+    /// disabled.
+    pub fn memory_space_access_enabled(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    /// If returns `true` the device can behave as a bus master; otherwise, the device can not generate PCI
+    /// accesses.
+    pub fn bus_master_enabled(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    /// If returns `true` the device can monitor Special Cycle operations; otherwise, the device will ignore them.
+    ///
+    /// For PCIe always set to `false`
+    pub fn special_cycles_enabled(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    /// If returns `true` the device can generate the Memory Write and Invalidate command; otherwise, the Memory
+    /// Write command must be used.
+    ///
+    /// For PCIe always set to `false`
+    pub fn memory_write_and_invalidate_enabled(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    /// If returns `true` the device does not respond to palette register writes and will snoop the data;
+    /// otherwise, the device will treat palette write accesses like all other accesses.
+    ///
+    /// For PCIe always set to `false`
+    pub fn vga_palette_snoop_enabled(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    /// If returns `true` the device will take its normal action when a parity error is detected; otherwise, when
+    /// an error is detected, the device will set bit 15 of the Status register, but will not assert PERR# and
+    /// will continue operation as normal.
+    pub fn parity_error_response_enabled(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    /// If returns `true` the SERR# driver is enabled; otherwise, the driver is disabled.
+    pub fn serr_enabled(&self) -> bool {
+        self.0.get_bit(8)
+    }
+
+    /// If returns `true` the device is allowed to generate fast back-to-back transactions; otherwise, fast
+    /// back-to-back transactions are only allowed to the same agent.
+    ///
+    /// For PCIe always set to `false`
+    pub fn fast_back_to_back_enabled(&self) -> bool {
+        self.0.get_bit(9)
+    }
+
+    /// If returns `true` the assertion of the device's INTx# signal is disabled; otherwise, assertion of the
+    /// signal is enabled.
+    pub fn interrupt_disabled(&self) -> bool {
+        self.0.get_bit(10)
+    }
+}
+
+impl ConfigRegister for CommandRegister {
+    type Raw = u16;
+
+    fn raw(&self) -> u16 {
+        self.0
+    }
+
+    fn from_raw(raw: u16) -> Self {
+        CommandRegister(raw)
+    }
+
+    fn reserved_mask() -> u16 {
+        0xf800
+    }
+}
+
+impl Debug for CommandRegister {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CommandRegister")
+            .field("io_space_access_enabled", &self.io_space_access_enabled())
+            .field(
+                "memory_space_access_enabled",
+                &self.memory_space_access_enabled(),
+            )
+            .field("bus_master_enabled", &self.bus_master_enabled())
+            .field("special_cycles_enabled", &self.special_cycles_enabled())
+            .field(
+                "memory_write_and_invalidate_enabled",
+                &self.memory_write_and_invalidate_enabled(),
+            )
+            .field(
+                "vga_palette_snoop_enabled",
+                &self.vga_palette_snoop_enabled(),
+            )
+            .field(
+                "parity_error_response_enabled",
+                &self.parity_error_response_enabled(),
+            )
+            .field("serr_enabled", &self.serr_enabled())
+            .field(
+                "fast_back_to_back_enabled",
+                &self.fast_back_to_back_enabled(),
+            )
+            .field("interrupt_disabled", &self.interrupt_disabled())
+            .finish()
+    }
+}
+
+/// Formats the raw value of the register. With memory space access and bus mastering enabled, `{:#06x}` gives
+/// `0x0006`.
+impl LowerHex for CommandRegister {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// Builds a new value for the Command register, starting from an existing one. The builder remembers the value
+/// it started from, so the bits that will actually change can be inspected with
+/// [`CommandRegisterBuilder::changed_mask`] before the value is written to the device.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandRegisterBuilder {
+    seed: u16,
+    value: u16,
+}
+
+impl CommandRegisterBuilder {
+    pub fn new(seed: CommandRegister) -> Self {
+        CommandRegisterBuilder {
+            seed: seed.0,
+            value: seed.0,
+        }
+    }
+
+    pub fn io_space_access(mut self, enabled: bool) -> Self {
+        self.value.set_bit(0, enabled);
+        self
+    }
+
+    pub fn memory_space_access(mut self, enabled: bool) -> Self {
+        self.value.set_bit(1, enabled);
+        self
+    }
+
+    pub fn bus_master(mut self, enabled: bool) -> Self {
+        self.value.set_bit(2, enabled);
+        self
+    }
+
+    pub fn special_cycles(mut self, enabled: bool) -> Self {
+        self.value.set_bit(3, enabled);
+        self
+    }
+
+    pub fn memory_write_and_invalidate(mut self, enabled: bool) -> Self {
+        self.value.set_bit(4, enabled);
+        self
+    }
+
+    pub fn vga_palette_snoop(mut self, enabled: bool) -> Self {
+        self.value.set_bit(5, enabled);
+        self
+    }
+
+    pub fn parity_error_response(mut self, enabled: bool) -> Self {
+        self.value.set_bit(6, enabled);
+        self
+    }
+
+    pub fn serr(mut self, enabled: bool) -> Self {
+        self.value.set_bit(8, enabled);
+        self
+    }
+
+    pub fn fast_back_to_back(mut self, enabled: bool) -> Self {
+        self.value.set_bit(9, enabled);
+        self
+    }
+
+    pub fn interrupt_disable(mut self, disabled: bool) -> Self {
+        self.value.set_bit(10, disabled);
+        self
+    }
+
+    /// The bits that differ between the value this builder was created from and the value it would build
+    pub fn changed_mask(&self) -> u16 {
+        self.seed ^ self.value
+    }
+
+    pub fn build(self) -> CommandRegister {
+        CommandRegister(self.value)
+    }
+}
+
+/// Pack a Command register value and a set of Status bits to clear into the dword at offset `0x04` of the header,
+/// with the Command register in the lower half and the Status register in the upper half. Only the RW1C bits of
+/// `status_clear_mask` are kept, so writing the result never sets a status bit by accident. This is the same as
+/// [`StatusRegister::clear_dword`], but starting from a [`CommandRegister`].
+pub fn command_status_dword(command: &CommandRegister, status_clear_mask: u16) -> u32 {
+    StatusRegister::clear_dword(command.raw(), status_clear_mask)
+}
+
+/// Error returned when a cache line size that the Cache Line Size register can't hold is requested. Holds the
+/// rejected size, in dwords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidCacheLineSize(pub u8);
+
+/// The Cache Line Size register of the header, which is in units of dwords rather than bytes. Only powers of two
+/// are legal, and `0` means the size hasn't been programmed. Functions ignore writes of other values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheLineSize(u8);
+
+impl CacheLineSize {
+    /// Returns [`InvalidCacheLineSize`] if `dwords` is neither `0` nor a power of two
+    pub fn from_dwords(dwords: u8) -> Result<CacheLineSize, InvalidCacheLineSize> {
+        if dwords == 0 || dwords.is_power_of_two() {
+            Ok(CacheLineSize(dwords))
+        } else {
+            Err(InvalidCacheLineSize(dwords))
+        }
+    }
+
+    /// Wrap the raw value of the register, without checking that it is legal
+    pub(crate) fn from_raw(value: u8) -> CacheLineSize {
+        CacheLineSize(value)
+    }
+
+    /// The size of a cache line, in dwords
+    pub fn as_dwords(&self) -> u8 {
+        self.0
+    }
+
+    /// The size of a cache line, in bytes
+    pub fn as_bytes(&self) -> u16 {
+        self.0 as u16 * 4
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    capability::{
+        AdvancedFeaturesCapability, AdvancedFeaturesError, PciCapability, PcieCapability,
+        PowerManagementCapability, PowerState,
+    },
+    ConfigRegionAccess, EndpointHeader,
+};
+
+/// How often the Transactions Pending bit of the PCI Express or Advanced Features capability is polled before an
+/// FLR
+const TRANSACTIONS_PENDING_POLLS: usize = 1000;
+
+/// How long a function is given to come out of an FLR before it is accessed again
+const FLR_DELAY_MS: u32 = 100;
+
+/// A way of resetting a function, as chosen by [`EndpointHeader::preferred_reset`]
+#[derive(Clone, Debug)]
+pub enum ResetMethod {
+    /// Function Level Reset through the PCI Express capability
+    PcieFlr(PcieCapability),
+    /// Function Level Reset through the Advanced Features capability
+    AdvancedFeaturesFlr(AdvancedFeaturesCapability),
+    /// Moving the function to D3hot and back to D0, which resets functions that don't set No_Soft_Reset
+    PowerManagement(PowerManagementCapability),
+    /// The function has no way of resetting itself, so the Secondary Bus Reset of the bridge above it has to be
+    /// used. This resets every function on that bus.
+    SecondaryBusReset,
+}
+
+/// Errors that can occur when resetting a function with [`EndpointHeader::reset`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetError {
+    /// The only available reset is a Secondary Bus Reset, which has to be done through the bridge above the
+    /// function
+    NeedsBridge,
+    /// The function still had transactions pending, so an FLR was not initiated
+    TransactionsPending,
+}
+
+impl ResetMethod {
+    pub(crate) fn find(header: &EndpointHeader, access: &impl ConfigRegionAccess) -> ResetMethod {
+        let mut af = None;
+        let mut pm = None;
+        for capability in header.capabilities(access) {
+            match capability {
+                PciCapability::PciExpress(pcie) if pcie.supports_flr(access) => {
+                    return ResetMethod::PcieFlr(pcie)
+                }
+                PciCapability::AdvancedFeatures(capability) if capability.flr_capable() => {
+                    af = Some(capability)
+                }
+                PciCapability::PowerManagement(capability) if !capability.no_soft_reset(access) => {
+                    pm = Some(capability)
+                }
+                _ => (),
+            }
+        }
+        af.map(ResetMethod::AdvancedFeaturesFlr)
+            .or(pm.map(ResetMethod::PowerManagement))
+            .unwrap_or(ResetMethod::SecondaryBusReset)
+    }
+
+    pub(crate) fn perform(
+        &self,
+        access: &impl ConfigRegionAccess,
+        mut wait_ms: impl FnMut(u32),
+    ) -> Result<(), ResetError> {
+        match self {
+            /*
+             * Only FLRs the function supports are picked, so these can't fail for lack of support.
+             */
+            ResetMethod::PcieFlr(pcie) => {
+                if !(0..TRANSACTIONS_PENDING_POLLS).any(|_| !pcie.transactions_pending(access)) {
+                    return Err(ResetError::TransactionsPending);
+                }
+                let _ = pcie.initiate_flr(access);
+                wait_ms(FLR_DELAY_MS);
+            }
+            ResetMethod::AdvancedFeaturesFlr(af) => {
+                if let Err(AdvancedFeaturesError::TransactionsPending) =
+                    af.initiate_flr(access, TRANSACTIONS_PENDING_POLLS)
+                {
+                    return Err(ResetError::TransactionsPending);
+                }
+                wait_ms(FLR_DELAY_MS);
+            }
+            /*
+             * The function needs 10ms to settle after each transition to or from D3hot.
+             */
+            ResetMethod::PowerManagement(pm) => {
+                pm.set_power_state(access, PowerState::D3Hot);
+                wait_ms(10);
+                pm.set_power_state(access, PowerState::D0);
+                wait_ms(10);
+            }
+            ResetMethod::SecondaryBusReset => return Err(ResetError::NeedsBridge),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{ConfigRegionAccess, PciAddress, PciHeader};
+use bit_field::BitField;
+use core::ptr;
+
+/// Errors that can occur when reading an option ROM image with [`ExpansionRom::read_image`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RomError {
+    /// The ROM doesn't start with the `0xaa55` signature, so there is no valid image in it. This is what is
+    /// usually seen on functions with an Expansion ROM BAR but no ROM fitted.
+    InvalidSignature,
+    /// The pointer to the PCI Data Structure points outside the ROM, or the structure doesn't start with the
+    /// `PCIR` signature
+    InvalidDataStructure,
+}
+
+/// The Expansion ROM BAR of a function, which maps its option ROM into memory space. Returned by
+/// [`crate::EndpointHeader::expansion_rom`].
+#[derive(Clone, Copy, Debug)]
+pub struct ExpansionRom {
+    address: PciAddress,
+    offset: u16,
+    base: u32,
+    size: u32,
+}
+
+impl ExpansionRom {
+    /// Probe the size of the Expansion ROM BAR at `offset` by writing all ones to its address bits, restoring it
+    /// afterwards. Decoding of the ROM is disabled while it is probed, so the function never decodes the all-ones
+    /// address. Returns `None` if it isn't implemented.
+    pub(crate) fn probe(
+        address: PciAddress,
+        offset: u16,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<ExpansionRom> {
+        /*
+         * Only bits 11 to 31 hold the address, so the smallest ROM is 2KiB.
+         */
+        const ADDRESS_MASK: u32 = 0xfffff800;
+        let (original, readback) = unsafe {
+            let original = access.read(address, offset);
+            access.write(address, offset, (original | ADDRESS_MASK) & !0x1);
+            let readback = access.read(address, offset);
+            access.write(address, offset, original);
+            (original, readback)
+        };
+        match readback & ADDRESS_MASK {
+            0 => None,
+            mask => Some(ExpansionRom {
+                address,
+                offset,
+                base: original & ADDRESS_MASK,
+                size: !mask + 1,
+            }),
+        }
+    }
+
+    /// The address the ROM is mapped at in memory space
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// The size of the ROM, in bytes
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Will be `true` if the function is decoding accesses to the ROM
+    pub fn is_enabled(&self, access: &impl ConfigRegionAccess) -> bool {
+        unsafe { access.read(self.address, self.offset) }.get_bit(0)
+    }
+
+    /// Enable or disable decoding of the ROM. Only the enable bit of the Expansion ROM BAR is changed, so the
+    /// address the ROM was given is kept while it is disabled, e.g. to free up the address space after reading it.
+    ///
+    /// ### Note
+    /// The function only decodes the ROM while Memory Space decoding is enabled in the Command register as well.
+    pub fn set_enabled(&self, access: &impl ConfigRegionAccess, enabled: bool) {
+        let mut bar = unsafe { access.read(self.address, self.offset) };
+        bar.set_bit(0, enabled);
+        unsafe { access.write(self.address, self.offset, bar) };
+    }
+
+    /// Enable decoding of the ROM and find the first image in it. The ROM is left enabled, along with Memory
+    /// Space decoding in the Command register, so the image can be copied out of it with
+    /// [`RomImage::copy_to`]. If no image is found, the Expansion ROM BAR and Command register are put back as
+    /// they were.
+    ///
+    /// `mapper` is called with the physical address and size of the ROM, and must return a pointer to where it
+    /// has been mapped, in the same way as for [`crate::Bar::map`]. The ROM must have been given an address
+    /// that doesn't overlap any other resource before this is called.
+    ///
+    /// # Safety
+    /// The pointer returned by `mapper` must be valid for reads of the whole ROM for as long as the returned
+    /// [`RomImage`] is used, and the ROM must stay enabled for that time.
+    pub unsafe fn read_image(
+        &self,
+        access: &impl ConfigRegionAccess,
+        mapper: impl Fn(u64, u64) -> *const u8,
+    ) -> Result<RomImage, RomError> {
+        let header = PciHeader::new(self.address);
+        let original_bar = access.read(self.address, self.offset);
+        let original_command = header.command(access);
+
+        self.set_enabled(access, true);
+        header.set_command(
+            access,
+            original_command.builder().memory_space_access(true).build(),
+        );
+
+        let image = self.find_image(mapper(self.base as u64, self.size as u64));
+        if image.is_err() {
+            access.write(self.address, self.offset, original_bar);
+            header.set_command(access, original_command);
+        }
+        image
+    }
+
+    /// Find the first image in the ROM mapped at `rom`
+    unsafe fn find_image(&self, rom: *const u8) -> Result<RomImage, RomError> {
+        let size = self.size as usize;
+        let byte = |offset: usize| ptr::read_volatile(rom.add(offset));
+        let word = |offset: usize| u16::from_le_bytes([byte(offset), byte(offset + 1)]);
+
+        if byte(0) != 0x55 || byte(1) != 0xaa {
+            return Err(RomError::InvalidSignature);
+        }
+
+        /*
+         * The PCI Data Structure is 24 bytes long, and must be dword-aligned.
+         */
+        let pcir = word(0x18) as usize;
+        if pcir + 0x18 > size || !pcir.is_multiple_of(4) {
+            return Err(RomError::InvalidDataStructure);
+        }
+        if [byte(pcir), byte(pcir + 1), byte(pcir + 2), byte(pcir + 3)] != *b"PCIR" {
+            return Err(RomError::InvalidDataStructure);
+        }
+
+        Ok(RomImage {
+            rom,
+            vendor_id: word(pcir + 0x4),
+            device_id: word(pcir + 0x6),
+            /*
+             * The image length is in units of 512 bytes. Images that claim to be longer than the ROM are cut
+             * short, so copying them can't read past its end.
+             */
+            length: (word(pcir + 0x10) as usize * 512).min(size),
+            code_type: byte(pcir + 0x14),
+            last_image: byte(pcir + 0x15).get_bit(7),
+        })
+    }
+}
+
+/// An option ROM image, as found by [`ExpansionRom::read_image`]
+#[derive(Debug)]
+pub struct RomImage {
+    rom: *const u8,
+    /// The Vendor ID of the function the image is for
+    pub vendor_id: u16,
+    /// The Device ID of the function the image is for
+    pub device_id: u16,
+    /// The length of the image, in bytes
+    pub length: usize,
+    /// The type of code in the image: `0x00` for x86 BIOS code, `0x01` for Open Firmware, `0x02` for HP PA
+    /// RISC, and `0x03` for UEFI
+    pub code_type: u8,
+    /// Will be `true` if this is the last image in the ROM
+    pub last_image: bool,
+}
+
+impl RomImage {
+    /// Copy the start of the image into `buffer`. Returns the number of bytes copied, which is the length of the
+    /// image or of `buffer`, whichever is shorter.
+    pub fn copy_to(&self, buffer: &mut [u8]) -> usize {
+        let count = self.length.min(buffer.len());
+        for (i, byte) in buffer[..count].iter_mut().enumerate() {
+            *byte = unsafe { ptr::read_volatile(self.rom.add(i)) };
+        }
+        count
+    }
+}
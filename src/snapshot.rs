@@ -0,0 +1,139 @@
+use crate::{capability::PciCapability, ConfigRegionAccess, PciAddress, MAX_BARS};
+use bit_field::BitField;
+
+/// The most capability registers a [`ConfigSnapshot`] can hold. A function with one each of the MSI, MSI-X and
+/// PCI Express capabilities needs at most 11 (5 for MSI, 1 for MSI-X and 5 for PCI Express).
+const MAX_SAVED_REGISTERS: usize = 16;
+
+/// A single dword of a capability, saved so it can be written back as-is
+#[derive(Clone, Copy, Debug)]
+struct SavedRegister {
+    offset: u16,
+    value: u32,
+}
+
+/// The writable configuration of an endpoint, as captured by [`crate::EndpointHeader::save_state`]. This holds
+/// the Command register, BARs, Expansion ROM BAR, Cache Line Size, Latency Timer and Interrupt Line of the
+/// header, as well as the configuration of the MSI, MSI-X and PCI Express capabilities.
+///
+/// Status registers are not saved, as their bits are either read-only or cleared by writing to them. A function
+/// with more capability registers than the snapshot has room for (e.g. one with several MSI capabilities) is only
+/// partially saved, which [`ConfigSnapshot::is_complete`] reports.
+#[derive(Clone, Debug)]
+pub struct ConfigSnapshot {
+    command: u16,
+    cache_line_and_latency: u16,
+    bars: [u32; MAX_BARS],
+    expansion_rom: u32,
+    interrupt_line: u8,
+    capabilities: [Option<SavedRegister>; MAX_SAVED_REGISTERS],
+    complete: bool,
+}
+
+impl ConfigSnapshot {
+    pub(crate) fn capture(
+        address: PciAddress,
+        access: &impl ConfigRegionAccess,
+        capabilities: impl Iterator<Item = PciCapability>,
+    ) -> ConfigSnapshot {
+        let read = |offset: u16| unsafe { access.read(address, offset) };
+
+        let mut snapshot = ConfigSnapshot {
+            command: read(0x04).get_bits(0..16) as u16,
+            cache_line_and_latency: read(0x0c).get_bits(0..16) as u16,
+            bars: [0; MAX_BARS],
+            expansion_rom: read(0x30),
+            interrupt_line: read(0x3c).get_bits(0..8) as u8,
+            capabilities: [None; MAX_SAVED_REGISTERS],
+            complete: true,
+        };
+        unsafe { access.read_block(address, 0x10, &mut snapshot.bars) };
+
+        let mut saved = 0;
+        let mut complete = true;
+        let mut save = |offset: u16, value: u32| match snapshot.capabilities.get_mut(saved) {
+            Some(register) => {
+                *register = Some(SavedRegister { offset, value });
+                saved += 1;
+            }
+            None => complete = false,
+        };
+        for capability in capabilities {
+            match capability {
+                PciCapability::Msi(msi) => {
+                    let base = msi.address().offset;
+                    save(base + 0x4, read(base + 0x4));
+                    if msi.is_64bit() {
+                        save(base + 0x8, read(base + 0x8));
+                    }
+                    save(base + msi.data_offset(), read(base + msi.data_offset()));
+                    if msi.has_per_vector_masking() {
+                        save(base + msi.mask_offset(), read(base + msi.mask_offset()));
+                    }
+                    /*
+                     * The Message Control register holds the enable bit, so it is saved last to be restored after
+                     * the message address and data.
+                     */
+                    save(base, read(base));
+                }
+                PciCapability::MsiX(address) => {
+                    /*
+                     * The table and pending bits live in memory space, so only the Message Control register (the
+                     * upper half of the first dword) is saved. The lower half is read-only.
+                     */
+                    save(address.offset, read(address.offset));
+                }
+                PciCapability::PciExpress(pcie) => {
+                    let base = pcie.address().offset;
+                    /*
+                     * The control registers share a dword with status registers that have RW1C bits, so only
+                     * the lower half is kept to avoid clearing them on restore.
+                     */
+                    let mut control =
+                        |offset: u16| save(base + offset, read(base + offset) & 0xffff);
+                    control(0x08);
+                    control(0x10);
+                    if pcie.slot_implemented() {
+                        control(0x18);
+                    }
+                    if pcie.version() >= 2 {
+                        control(0x28);
+                        control(0x30);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        snapshot.complete = complete;
+        snapshot
+    }
+
+    /// Will be `false` if the function had more capability registers than the snapshot could hold, in which case
+    /// those that didn't fit are not restored
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub(crate) fn restore(&self, address: PciAddress, access: &impl ConfigRegionAccess) {
+        let write = |offset: u16, value: u32| unsafe { access.write(address, offset, value) };
+
+        /*
+         * Turn off decoding while the BARs are rewritten, so the function never decodes a half-restored address.
+         * The upper half of the dword is the Status register, which is left alone by writing zeroes to it.
+         */
+        write(0x04, (self.command & !0x7) as u32);
+        for (slot, bar) in self.bars.iter().enumerate() {
+            write(0x10 + slot as u16 * 4, *bar);
+        }
+        write(0x30, self.expansion_rom);
+        write(0x0c, self.cache_line_and_latency as u32);
+        write(0x3c, self.interrupt_line as u32);
+
+        for register in self.capabilities.iter().flatten() {
+            write(register.offset, register.value);
+        }
+
+        write(0x04, self.command as u32);
+    }
+}
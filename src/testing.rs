@@ -0,0 +1,307 @@
+//! Helpers for testing code that uses this crate without real hardware. Requires the `testing` feature, which
+//! also pulls in `alloc`.
+
+pub use crate::access::AccessKind;
+use crate::{
+    capability::CapabilityId, debug_assert_dword_aligned, ConfigRegionAccess, HeaderType,
+    PciAddress,
+};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+
+/// Size of the configuration space of a single PCI Express function
+const CONFIG_SPACE_SIZE: usize = 0x1000;
+
+/// Offset of the first capability laid out by [`ConfigSpaceBuilder`]. This is directly after the type-0 and
+/// type-1 headers.
+const FIRST_CAPABILITY_OFFSET: usize = 0x40;
+
+/// A synthetic configuration space for a single function, backed by memory. It can be used as a
+/// [`ConfigRegionAccess`], in which case it responds to every address with the same configuration space.
+///
+/// Reads and writes go straight to the underlying memory, so the special behaviour of some registers (e.g. RW1C
+/// bits) is not emulated. The exception is bits marked read-only with [`ConfigSpaceBuilder::read_only_bits`],
+/// which keep their value when written, e.g. to emulate the hardwired bits that BAR size probing relies on.
+/// Accesses past the end of the configuration space read as all ones and ignore writes.
+pub struct ConfigSpace {
+    dwords: [Cell<u32>; CONFIG_SPACE_SIZE / 4],
+    read_only: [u32; CONFIG_SPACE_SIZE / 4],
+}
+
+impl ConfigSpace {
+    /// Create a configuration space from its raw bytes. `bytes` can be shorter than 4096 bytes, in which case
+    /// the rest of the space is zeroed.
+    pub fn from_bytes(bytes: &[u8]) -> ConfigSpace {
+        let space = ConfigSpace {
+            dwords: core::array::from_fn(|_| Cell::new(0)),
+            read_only: [0; CONFIG_SPACE_SIZE / 4],
+        };
+        for (dword, chunk) in space.dwords.iter().zip(bytes.chunks(4)) {
+            let mut raw = [0; 4];
+            raw[..chunk.len()].copy_from_slice(chunk);
+            dword.set(u32::from_le_bytes(raw));
+        }
+        space
+    }
+}
+
+impl ConfigRegionAccess for ConfigSpace {
+    fn function_exists(&self, _address: PciAddress) -> bool {
+        self.dwords[0].get() & 0xffff != 0xffff
+    }
+
+    fn supports_extended(&self) -> bool {
+        true
+    }
+
+    unsafe fn read(&self, _address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        self.dwords
+            .get(offset as usize / 4)
+            .map(Cell::get)
+            .unwrap_or(0xffffffff)
+    }
+
+    unsafe fn write(&self, _address: PciAddress, offset: u16, value: u32) {
+        debug_assert_dword_aligned(offset);
+        let index = offset as usize / 4;
+        if let Some(dword) = self.dwords.get(index) {
+            let read_only = self.read_only[index];
+            dword.set((value & !read_only) | (dword.get() & read_only));
+        }
+    }
+}
+
+/// Declaratively build the configuration space of a function:
+/// ```ignore
+/// let space = ConfigSpaceBuilder::new()
+///     .vendor(0x8086)
+///     .device(0x100e)
+///     .class(0x02, 0x00, 0x00)
+///     .add_capability(CapabilityId::Msi, &[0x80, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+///     .build();
+/// ```
+///
+/// Capabilities are laid out one after another from offset `0x40`, and the capability list (the Capabilities
+/// Pointer, the next pointers and bit 4 of the Status register) is filled in automatically.
+pub struct ConfigSpaceBuilder {
+    bytes: [u8; CONFIG_SPACE_SIZE],
+    read_only: [u32; CONFIG_SPACE_SIZE / 4],
+    next_capability: usize,
+    last_capability: Option<usize>,
+}
+
+impl ConfigSpaceBuilder {
+    /// Create a builder for an endpoint with all registers zeroed
+    pub fn new() -> ConfigSpaceBuilder {
+        ConfigSpaceBuilder {
+            bytes: [0; CONFIG_SPACE_SIZE],
+            read_only: [0; CONFIG_SPACE_SIZE / 4],
+            next_capability: FIRST_CAPABILITY_OFFSET,
+            last_capability: None,
+        }
+    }
+
+    pub fn vendor(self, vendor: u16) -> Self {
+        self.u16_at(0x00, vendor)
+    }
+
+    pub fn device(self, device: u16) -> Self {
+        self.u16_at(0x02, device)
+    }
+
+    /// Set the raw value of the Command register
+    pub fn command(self, command: u16) -> Self {
+        self.u16_at(0x04, command)
+    }
+
+    /// Set the raw value of the Status register. The Capabilities List bit is managed by the builder and is
+    /// always preserved.
+    pub fn status(self, status: u16) -> Self {
+        let capability_list = self.bytes[0x06] & 0x10;
+        let mut builder = self.u16_at(0x06, status);
+        builder.bytes[0x06] = (builder.bytes[0x06] & !0x10) | capability_list;
+        builder
+    }
+
+    pub fn revision(self, revision: u8) -> Self {
+        self.u8_at(0x08, revision)
+    }
+
+    pub fn class(self, base: u8, sub: u8, interface: u8) -> Self {
+        self.u8_at(0x09, interface)
+            .u8_at(0x0a, sub)
+            .u8_at(0x0b, base)
+    }
+
+    pub fn header_type(self, header_type: HeaderType) -> Self {
+        let raw = match header_type {
+            HeaderType::Endpoint => 0x00,
+            HeaderType::PciPciBridge => 0x01,
+            HeaderType::CardBusBridge => 0x02,
+            HeaderType::Unknown(raw) => raw & 0x7f,
+        };
+        let multifunction = self.bytes[0x0e] & 0x80;
+        self.u8_at(0x0e, raw | multifunction)
+    }
+
+    pub fn multifunction(self, multifunction: bool) -> Self {
+        let header_type = self.bytes[0x0e] & 0x7f;
+        self.u8_at(0x0e, header_type | if multifunction { 0x80 } else { 0 })
+    }
+
+    /// Set the raw value of the BAR in the given slot
+    pub fn bar(self, slot: u8, value: u32) -> Self {
+        self.u32_at(0x10 + slot as usize * 4, value)
+    }
+
+    /// Set an arbitrary dword of the configuration space, for registers the builder doesn't otherwise have a
+    /// method for
+    pub fn u32_at(mut self, offset: usize, value: u32) -> Self {
+        self.bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Make the bits set in `mask` of the dword at `offset` read-only, so that writes leave them unchanged. For
+    /// example, a 4KiB 32-bit memory BAR in slot 0 has its twelve lowest bits hardwired, which is
+    /// `.read_only_bits(0x10, 0xfff)`.
+    pub fn read_only_bits(mut self, offset: usize, mask: u32) -> Self {
+        self.read_only[offset / 4] |= mask;
+        self
+    }
+
+    /// Append a capability to the capability list. `body` is the contents of the capability after its two-byte
+    /// header (the ID and next pointer), so e.g. for MSI it starts with the Message Control register.
+    ///
+    /// # Panics
+    /// Panics if the capability doesn't fit in the 256-byte PCI configuration space.
+    pub fn add_capability(mut self, id: CapabilityId, body: &[u8]) -> Self {
+        let offset = self.next_capability;
+        let end = offset + 2 + body.len();
+        assert!(
+            end <= 0x100,
+            "capability {} at offset {:#x} doesn't fit in configuration space",
+            id,
+            offset
+        );
+
+        self.bytes[offset] = id.into();
+        self.bytes[offset + 1] = 0;
+        self.bytes[offset + 2..end].copy_from_slice(body);
+
+        match self.last_capability {
+            Some(last) => self.bytes[last + 1] = offset as u8,
+            None => {
+                self.bytes[0x34] = offset as u8;
+                self.bytes[0x06] |= 0x10;
+            }
+        }
+        self.last_capability = Some(offset);
+        self.next_capability = (end + 3) & !3;
+        self
+    }
+
+    pub fn build(self) -> ConfigSpace {
+        let mut space = ConfigSpace::from_bytes(&self.bytes);
+        space.read_only = self.read_only;
+        space
+    }
+
+    fn u8_at(mut self, offset: usize, value: u8) -> Self {
+        self.bytes[offset] = value;
+        self
+    }
+
+    fn u16_at(mut self, offset: usize, value: u16) -> Self {
+        self.bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+        self
+    }
+}
+
+impl Default for ConfigSpaceBuilder {
+    fn default() -> Self {
+        ConfigSpaceBuilder::new()
+    }
+}
+
+/// A single access recorded by [`TracingAccess`]. For reads, `value` is the value that was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub address: PciAddress,
+    pub offset: u16,
+    pub kind: AccessKind,
+    pub value: u32,
+}
+
+/// Wraps another access (e.g. a [`ConfigSpace`]) and records every read and write made through it, in order.
+/// This lets tests check the exact sequence of configuration accesses a driver makes, e.g. that it disables
+/// decoding before moving a BAR.
+pub struct TracingAccess<A: ConfigRegionAccess> {
+    inner: A,
+    trace: RefCell<Vec<TraceEntry>>,
+}
+
+impl<A: ConfigRegionAccess> TracingAccess<A> {
+    pub fn new(inner: A) -> TracingAccess<A> {
+        TracingAccess {
+            inner,
+            trace: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    /// The accesses recorded so far, oldest first
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.trace.borrow().clone()
+    }
+
+    /// Return the accesses recorded so far and start a new trace
+    pub fn take_trace(&self) -> Vec<TraceEntry> {
+        self.trace.take()
+    }
+
+    /// The writes recorded so far, oldest first, as `(offset, value)` pairs
+    pub fn writes(&self) -> Vec<(u16, u32)> {
+        self.trace
+            .borrow()
+            .iter()
+            .filter(|entry| entry.kind == AccessKind::Write)
+            .map(|entry| (entry.offset, entry.value))
+            .collect()
+    }
+
+    fn record(&self, address: PciAddress, offset: u16, kind: AccessKind, value: u32) {
+        self.trace.borrow_mut().push(TraceEntry {
+            address,
+            offset,
+            kind,
+            value,
+        });
+    }
+}
+
+impl<A: ConfigRegionAccess> ConfigRegionAccess for TracingAccess<A> {
+    fn function_exists(&self, address: PciAddress) -> bool {
+        self.inner.function_exists(address)
+    }
+
+    fn supports_extended(&self) -> bool {
+        self.inner.supports_extended()
+    }
+
+    unsafe fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        debug_assert_dword_aligned(offset);
+        let value = self.inner.read(address, offset);
+        self.record(address, offset, AccessKind::Read, value);
+        value
+    }
+
+    unsafe fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        debug_assert_dword_aligned(offset);
+        self.record(address, offset, AccessKind::Write, value);
+        self.inner.write(address, offset, value)
+    }
+}